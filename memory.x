@@ -1,15 +1,26 @@
+/* Runs from the active slot behind the bootloader in bootloader/, which owns BOOT2 */
 MEMORY {
-    BOOT2 : ORIGIN = 0x10000000, LENGTH = 0x100
-    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100
-    RAM   : ORIGIN = 0x20000000, LENGTH = 256K
+    BOOT2            : ORIGIN = 0x10000000, LENGTH = 0x100
+    BOOTLOADER_STATE : ORIGIN = 0x10006000, LENGTH = 4K
+    FLASH            : ORIGIN = 0x10007000, LENGTH = 1000K
+    /* One sector larger than the active slot, for the swap */
+    DFU              : ORIGIN = 0x10101000, LENGTH = 1004K
+    /* The last 8K hold the saved logo and settings, see settings.rs */
+    RAM              : ORIGIN = 0x20000000, LENGTH = 256K
+    /* Start of SRAM4, kept out of RAM so neither our stack nor the bootloader's reaches it.
+       The crash record lives here across resets, see supervisor.rs */
+    CRASH            : ORIGIN = 0x20040000, LENGTH = 256
 }
 
-EXTERN(BOOT2_FIRMWARE)
-
 SECTIONS {
-    /* ### Boot loader */
-    .boot2 ORIGIN(BOOT2) :
+    .crash (NOLOAD) : ALIGN(4)
     {
-        KEEP(*(.boot2));
-    } > BOOT2
-} INSERT BEFORE .text;
+        KEEP(*(.crash .crash.*));
+    } > CRASH
+} INSERT AFTER .uninit;
+
+__bootloader_state_start = ORIGIN(BOOTLOADER_STATE) - ORIGIN(BOOT2);
+__bootloader_state_end = ORIGIN(BOOTLOADER_STATE) + LENGTH(BOOTLOADER_STATE) - ORIGIN(BOOT2);
+
+__bootloader_dfu_start = ORIGIN(DFU) - ORIGIN(BOOT2);
+__bootloader_dfu_end = ORIGIN(DFU) + LENGTH(DFU) - ORIGIN(BOOT2);
@@ -43,6 +43,7 @@ impl ButtonScanner {
 
 use crate::events::ButtonEvent;
 use crate::events::ButtonId;
+use crate::supervisor::{self, TaskId};
 use crate::BUTTON_CH;
 use embassy_time::Timer;
 
@@ -53,6 +54,7 @@ pub async fn button_scanner_task(mut scanner: ButtonScanner) {
     let mut last_buttons = [false; 12];
 
     loop {
+        supervisor::beat(TaskId::Buttons);
         for (i, id, x, y) in [
             (0, ButtonId::MetronomeStart, 1, 0),
             (1, ButtonId::MetronomeStop, 0, 0),
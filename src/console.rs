@@ -0,0 +1,331 @@
+use crate::events::{Action, ButtonEvent, ButtonId};
+use crate::http;
+use crate::logger::{self, Level};
+use crate::metronome;
+use crate::netutil;
+use crate::state::Core;
+use crate::timers::TimerId;
+use crate::{ACTION_UPSTREAM, BUTTON_CH, STATE};
+use common::mem::str::StaticString;
+use embassy_net::tcp::TcpSocket;
+use embassy_net::Stack;
+use embassy_time::{Duration, Timer};
+
+const CONSOLE_PORT: u16 = 23;
+/// Idle sessions are dropped so a forgotten client doesn't hold the only socket.
+const TIMEOUT: Duration = Duration::from_secs(300);
+/// How long an injected press is held, about as long as a quick real one.
+const PRESS_HOLD: Duration = Duration::from_millis(50);
+const LINE_LEN: usize = 96;
+
+const HELP: &str =
+    "status | cores | cue | bpm | press <button>... | metronome <bpm>|start|stop|tap\r\n\
+timer <sw|a|b> start|reset | pin <web pin> | set <name> <value> | reload | quit\r\n\
+buttons: next prev start stop menu shift tempo+ tempo- bright+ bright- metro-start metro-stop\r\n\
+settings: name core_ip core_port backup_ip backup2_ip backup3_ip master mirror net_mode\r\n\
+  static_ip netmask gateway log_host osc_host osc_port osc_<button> tempo led\r\n";
+
+/// Telnet clients open with option negotiation, which has to be kept out of the line.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Telnet {
+    Data,
+    Command,
+    Option,
+    Subnegotiation,
+}
+
+enum Outcome {
+    Reply(StaticString<128>),
+    Help,
+    Quit,
+}
+
+#[embassy_executor::task]
+pub async fn console_task(stack: Stack<'static>) {
+    let mut rx_buffer = [0; 256];
+    let mut tx_buffer = [0; 512];
+
+    loop {
+        let mut socket = TcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer);
+        socket.set_timeout(Some(TIMEOUT));
+        if socket.accept(CONSOLE_PORT).await.is_err() {
+            continue;
+        }
+        logger::log(Level::Info, "console", format_args!("session opened"));
+        session(stack, &mut socket).await;
+        logger::log(Level::Info, "console", format_args!("session closed"));
+        socket.close();
+        let _ = socket.flush().await;
+    }
+}
+
+async fn session(stack: Stack<'static>, socket: &mut TcpSocket<'_>) {
+    let mut chunk = [0u8; 64];
+    let mut line = [0u8; LINE_LEN];
+    let mut len = 0;
+    let mut overflow = false;
+    let mut telnet = Telnet::Data;
+    // Settings are behind the web PIN here too, entered once per session and checked on every
+    // change in case it has changed since
+    let mut pin = None;
+
+    if netutil::write_all(
+        socket,
+        b"ClicKS controller console, 'help' for commands\r\n> ",
+    )
+    .await
+    .is_err()
+    {
+        return;
+    }
+
+    loop {
+        let n = match socket.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => n,
+        };
+        for &byte in &chunk[..n] {
+            telnet = match (telnet, byte) {
+                (Telnet::Data, 0xFF) => Telnet::Command,
+                (Telnet::Data, _) => Telnet::Data,
+                // WILL, WONT, DO and DONT carry one option byte
+                (Telnet::Command, 251..=254) => Telnet::Option,
+                (Telnet::Command, 250) => Telnet::Subnegotiation,
+                (Telnet::Command, _) => Telnet::Data,
+                (Telnet::Option, _) => Telnet::Data,
+                // Ends at IAC SE, the IAC is enough to hand back to Command
+                (Telnet::Subnegotiation, 0xFF) => Telnet::Command,
+                (Telnet::Subnegotiation, _) => Telnet::Subnegotiation,
+            };
+            if telnet != Telnet::Data || byte == 0xFF {
+                continue;
+            }
+
+            match byte {
+                b'\n' => {
+                    let reply = if overflow {
+                        Outcome::Reply(StaticString::new("error: line too long"))
+                    } else {
+                        let text = core::str::from_utf8(&line[..len]).unwrap_or_default();
+                        execute(stack, text.trim(), &mut pin).await
+                    };
+                    len = 0;
+                    overflow = false;
+
+                    let written = match reply {
+                        Outcome::Quit => return,
+                        Outcome::Help => netutil::write_all(socket, HELP.as_bytes()).await,
+                        Outcome::Reply(reply) if reply.str().is_empty() => Ok(()),
+                        Outcome::Reply(reply) => {
+                            match netutil::write_all(socket, reply.str().as_bytes()).await {
+                                Ok(()) => netutil::write_all(socket, b"\r\n").await,
+                                err => err,
+                            }
+                        }
+                    };
+                    if written.is_err() || netutil::write_all(socket, b"> ").await.is_err() {
+                        return;
+                    }
+                }
+                b'\r' | 0 => {}
+                _ if len == LINE_LEN => overflow = true,
+                _ => {
+                    line[len] = byte;
+                    len += 1;
+                }
+            }
+        }
+        if socket.flush().await.is_err() {
+            return;
+        }
+    }
+}
+
+fn reply(args: core::fmt::Arguments) -> Outcome {
+    let mut buf = [0u8; 128];
+    Outcome::Reply(StaticString::new(
+        format_no_std::show(&mut buf, args).unwrap_or("error: reply too long"),
+    ))
+}
+
+fn ok() -> Outcome {
+    Outcome::Reply(StaticString::new("ok"))
+}
+
+fn error(msg: &str) -> Outcome {
+    reply(format_args!("error: {}", msg))
+}
+
+fn timer(name: &str) -> Option<TimerId> {
+    Some(match name {
+        "sw" | "stopwatch" => TimerId::Stopwatch,
+        "a" => TimerId::CountdownA,
+        "b" => TimerId::CountdownB,
+        _ => return None,
+    })
+}
+
+/// Holds each button down in turn, then lets go in reverse, so `press shift next` is a chord.
+/// Goes through the translator like a real press, so it means whatever the current mode says.
+async fn press(names: &str) -> Outcome {
+    let mut ids = [ButtonId::Next; 4];
+    let mut count = 0;
+    for name in names.split_ascii_whitespace() {
+        let Some(id) = ButtonId::from_name(name) else {
+            return error("unknown button");
+        };
+        let Some(slot) = ids.get_mut(count) else {
+            return error("too many buttons");
+        };
+        *slot = id;
+        count += 1;
+    }
+    if count == 0 {
+        return error("press what?");
+    }
+
+    for &id in &ids[..count] {
+        BUTTON_CH.send(ButtonEvent { id, pressed: true }).await;
+        Timer::after(PRESS_HOLD).await;
+    }
+    for &id in ids[..count].iter().rev() {
+        BUTTON_CH.send(ButtonEvent { id, pressed: false }).await;
+    }
+    ok()
+}
+
+async fn execute(stack: Stack<'static>, line: &str, pin: &mut Option<StaticString<32>>) -> Outcome {
+    let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+    let args = args.trim();
+
+    match command {
+        "" => Outcome::Reply(StaticString::empty()),
+        "help" => Outcome::Help,
+        "quit" | "exit" => Outcome::Quit,
+        "status" => {
+            let state = STATE.lock().await;
+            let core = state.core();
+            let [a, b, c, d] = core.ip.addr;
+            reply(format_args!(
+                "{} core {} {}.{}.{}.{}:{} cue {} bar {}.{} bpm {}",
+                state.link.label(),
+                Core::role(state.active_core),
+                a,
+                b,
+                c,
+                d,
+                core.ip.port,
+                state.cue_idx,
+                state.beat.bar_number,
+                state.beat.count,
+                state.beat.tempo(),
+            ))
+        }
+        "cores" => {
+            // One line per core, the active one starred, kept short to fit a single reply
+            let state = STATE.lock().await;
+            let mut buf = [0u8; 128];
+            let mut len = 0;
+            for (slot, core) in state.cores.iter().enumerate() {
+                if !core.configured() {
+                    continue;
+                }
+                let [a, b, c, d] = core.ip.addr;
+                let line = format_no_std::show(
+                    &mut buf[len..],
+                    format_args!(
+                        "{}{} {}.{}.{}.{} {}{}",
+                        if len == 0 { "" } else { "\r\n" },
+                        Core::role(slot),
+                        a,
+                        b,
+                        c,
+                        d,
+                        core.last_send.label(),
+                        if slot == state.active_core {
+                            " active"
+                        } else {
+                            ""
+                        },
+                    ),
+                )
+                .unwrap_or_default();
+                len += line.len();
+            }
+            reply(format_args!(
+                "{}",
+                core::str::from_utf8(&buf[..len]).unwrap_or_default()
+            ))
+        }
+        "cue" => {
+            let state = STATE.lock().await;
+            reply(format_args!(
+                "{} {}",
+                state.cue_idx,
+                state.cue_metadata.human_ident.str()
+            ))
+        }
+        "bpm" => reply(format_args!("{}", STATE.lock().await.beat.tempo())),
+        "press" => press(args).await,
+        "metronome" => {
+            let action = match args {
+                "start" => Action::MetronomeStart,
+                "stop" => Action::MetronomeStop,
+                "tap" => Action::MetronomeTempoTap,
+                bpm => match bpm.parse::<i64>() {
+                    Ok(bpm) if (metronome::MIN_BPM..=metronome::MAX_BPM).contains(&bpm) => {
+                        Action::MetronomeSetTempo(bpm)
+                    }
+                    _ => return error("expected a tempo of 20 to 400, start, stop or tap"),
+                },
+            };
+            ACTION_UPSTREAM.send(action).await;
+            ok()
+        }
+        "timer" => {
+            let (name, verb) = args.split_once(' ').unwrap_or((args, ""));
+            let Some(id) = timer(name) else {
+                return error("unknown timer");
+            };
+            let action = match verb.trim() {
+                "start" | "stop" => Action::TimerStartStop(id),
+                "reset" => Action::TimerReset(id),
+                _ => return error("expected start or reset"),
+            };
+            ACTION_UPSTREAM.send(action).await;
+            ok()
+        }
+        "pin" => {
+            if STATE.lock().await.web_pin.len() == 0 {
+                return error("set a web PIN in the menu first");
+            }
+            *pin = Some(StaticString::new(args));
+            if http::pin_ok(*pin).await {
+                ok()
+            } else {
+                error("wrong PIN")
+            }
+        }
+        "set" => {
+            if !http::pin_ok(*pin).await {
+                return error("enter the web PIN first, pin <web pin>");
+            }
+            let (name, value) = args.split_once(' ').unwrap_or((args, ""));
+            // Checked first, applying runs the side effects of a change even when nothing did
+            if !http::is_setting(name) {
+                return error("unknown setting");
+            }
+            let setting = (name, StaticString::new(value.trim()));
+            if http::apply_settings(stack, core::iter::once(setting)).await {
+                ok()
+            } else {
+                error("bad value")
+            }
+        }
+        "reload" => {
+            ACTION_UPSTREAM.send(Action::ReloadConnection).await;
+            ok()
+        }
+        _ => error("unknown command, try help"),
+    }
+}
@@ -0,0 +1,163 @@
+use crate::logger::{self, Level};
+use crate::ActionChannel;
+use common::mem::str::StaticString;
+use portable_atomic::{AtomicU32, Ordering};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelId {
+    Upstream,
+    Control,
+    Ui,
+    Ux,
+    Led,
+    Metronome,
+    Timer,
+}
+
+impl ChannelId {
+    pub const ALL: [ChannelId; 7] = [
+        ChannelId::Upstream,
+        ChannelId::Control,
+        ChannelId::Ui,
+        ChannelId::Ux,
+        ChannelId::Led,
+        ChannelId::Metronome,
+        ChannelId::Timer,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChannelId::Upstream => "UPS",
+            ChannelId::Control => "CTL",
+            ChannelId::Ui => "UI",
+            ChannelId::Ux => "UX",
+            ChannelId::Led => "LED",
+            ChannelId::Metronome => "MET",
+            ChannelId::Timer => "TMR",
+        }
+    }
+}
+
+struct ChannelCounters {
+    dropped: AtomicU32,
+    peak: AtomicU32,
+}
+
+impl ChannelCounters {
+    const fn new() -> Self {
+        Self {
+            dropped: AtomicU32::new(0),
+            peak: AtomicU32::new(0),
+        }
+    }
+}
+
+static CHANNELS: [ChannelCounters; ChannelId::ALL.len()] =
+    [const { ChannelCounters::new() }; ChannelId::ALL.len()];
+
+static PACKETS_RX: AtomicU32 = AtomicU32::new(0);
+static PACKETS_RX_INVALID: AtomicU32 = AtomicU32::new(0);
+static PACKETS_TX: AtomicU32 = AtomicU32::new(0);
+static PACKETS_TX_FAILED: AtomicU32 = AtomicU32::new(0);
+
+pub fn record_drop(ch: ChannelId) {
+    CHANNELS[ch as usize]
+        .dropped
+        .fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_depth(ch: ChannelId, depth: usize) {
+    CHANNELS[ch as usize]
+        .peak
+        .fetch_max(depth as u32, Ordering::Relaxed);
+}
+
+/// Non-blocking send that keeps the drop and high-water counters of `ch` up to date.
+pub fn try_forward(channel: &ActionChannel, ch: ChannelId, action: crate::events::Action) {
+    match channel.try_send(action) {
+        Ok(()) => record_depth(ch, channel.len()),
+        Err(_) => record_drop(ch),
+    }
+}
+
+pub fn record_rx(valid: bool) {
+    PACKETS_RX.fetch_add(1, Ordering::Relaxed);
+    if !valid {
+        PACKETS_RX_INVALID.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+pub fn record_tx(ok: bool) {
+    PACKETS_TX.fetch_add(1, Ordering::Relaxed);
+    if !ok {
+        PACKETS_TX_FAILED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct ChannelSnapshot {
+    pub dropped: u32,
+    pub peak: u32,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Snapshot {
+    pub channels: [ChannelSnapshot; ChannelId::ALL.len()],
+    pub rx: u32,
+    pub rx_invalid: u32,
+    pub tx: u32,
+    pub tx_failed: u32,
+}
+
+pub fn snapshot() -> Snapshot {
+    let mut snap = Snapshot {
+        rx: PACKETS_RX.load(Ordering::Relaxed),
+        rx_invalid: PACKETS_RX_INVALID.load(Ordering::Relaxed),
+        tx: PACKETS_TX.load(Ordering::Relaxed),
+        tx_failed: PACKETS_TX_FAILED.load(Ordering::Relaxed),
+        ..Default::default()
+    };
+    for (out, counters) in snap.channels.iter_mut().zip(CHANNELS.iter()) {
+        out.dropped = counters.dropped.load(Ordering::Relaxed);
+        out.peak = counters.peak.load(Ordering::Relaxed);
+    }
+    snap
+}
+
+pub const NUM_LINES: usize = ChannelId::ALL.len() + 2;
+
+/// One line of the diagnostics screen, 21 characters wide.
+pub fn line(snap: &Snapshot, idx: usize) -> StaticString<32> {
+    let mut buf = [0u8; 32];
+    let s = if let Some(ch) = ChannelId::ALL.get(idx) {
+        let c = snap.channels[*ch as usize];
+        format_no_std::show(
+            &mut buf,
+            format_args!("{: <3} drop{: >5} pk{: >2}", ch.label(), c.dropped, c.peak),
+        )
+    } else if idx == ChannelId::ALL.len() {
+        format_no_std::show(
+            &mut buf,
+            format_args!("RX{: >7} bad{: >6}", snap.rx, snap.rx_invalid),
+        )
+    } else {
+        format_no_std::show(
+            &mut buf,
+            format_args!("TX{: >7} err{: >6}", snap.tx, snap.tx_failed),
+        )
+    }
+    .unwrap_or_default();
+    StaticString::new(s)
+}
+
+/// Queues every line for the log host, whatever the log level, as asked for from the menu.
+pub fn send_report() {
+    let snap = snapshot();
+    for idx in 0..NUM_LINES {
+        logger::send(
+            Level::Info,
+            "diagnostics",
+            format_args!("{}", line(&snap, idx).str()),
+        );
+    }
+}
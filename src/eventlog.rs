@@ -0,0 +1,81 @@
+use common::event::EventDescription;
+use common::mem::str::StaticString;
+use embassy_time::Instant;
+
+/// How many events the log screen can scroll back through.
+pub const LEN: usize = 16;
+
+#[derive(Clone, Copy)]
+pub struct Entry {
+    pub at: Instant,
+    /// The cue we were in when it happened.
+    pub cue_idx: u16,
+    pub text: StaticString<16>,
+}
+
+/// The most recent events from the core, oldest dropped first.
+#[derive(Clone, Copy)]
+pub struct EventLog {
+    entries: [Option<Entry>; LEN],
+    next: usize,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventLog {
+    pub const fn new() -> Self {
+        Self {
+            entries: [None; LEN],
+            next: 0,
+        }
+    }
+
+    pub fn push(&mut self, entry: Entry) {
+        self.entries[self.next] = Some(entry);
+        self.next = (self.next + 1) % LEN;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.iter().flatten().count()
+    }
+
+    /// Newest first.
+    pub fn recent(&self) -> impl Iterator<Item = &Entry> {
+        (1..=LEN)
+            .map(move |i| &self.entries[(self.next + LEN - i) % LEN])
+            .map_while(Option::as_ref)
+    }
+}
+
+/// Short text for an event, as shown in the log and notifications. `kind` is the event's
+/// variant number as sent by the core, the one thing we know about events newer than us.
+pub fn describe(event: &EventDescription, kind: u8) -> StaticString<16> {
+    match event {
+        EventDescription::RehearsalMarkEvent { label } => {
+            let mut buf = [0u8; 16];
+            let s = format_no_std::show(&mut buf, format_args!("Mark {}", fit(label.str(), 11)))
+                .unwrap_or("Mark");
+            StaticString::new(s)
+        }
+        // Newer than this firmware, we have no wording for it
+        _ => {
+            let mut buf = [0u8; 16];
+            let s =
+                format_no_std::show(&mut buf, format_args!("Event {}", kind)).unwrap_or("Event");
+            StaticString::new(s)
+        }
+    }
+}
+
+/// The longest start of `s` that fits in `len` bytes without splitting a character.
+fn fit(s: &str, len: usize) -> &str {
+    let mut end = s.len().min(len);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
@@ -1,4 +1,6 @@
-use crate::{led::LED, textentry::TextEntryContext};
+use crate::{
+    led::LED, menu::MenuPage, state::LinkStatus, textentry::TextEntryContext, timers::TimerId,
+};
 use common::{
     beat::Beat,
     cue::CueMetadata,
@@ -23,6 +25,49 @@ pub enum ButtonId {
     Start,
 }
 
+impl ButtonId {
+    pub const ALL: [ButtonId; 12] = [
+        ButtonId::MetronomeStart,
+        ButtonId::MetronomeStop,
+        ButtonId::Shift,
+        ButtonId::Menu,
+        ButtonId::MetronomeTempoPlus,
+        ButtonId::MetronomeTempoMinus,
+        ButtonId::MetronomeBrightPlus,
+        ButtonId::MetronomeBrightMinus,
+        ButtonId::Next,
+        ButtonId::Previous,
+        ButtonId::Stop,
+        ButtonId::Start,
+    ];
+
+    /// Short name used by the console and in OSC addresses.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ButtonId::MetronomeStart => "metro-start",
+            ButtonId::MetronomeStop => "metro-stop",
+            ButtonId::Shift => "shift",
+            ButtonId::Menu => "menu",
+            ButtonId::MetronomeTempoPlus => "tempo+",
+            ButtonId::MetronomeTempoMinus => "tempo-",
+            ButtonId::MetronomeBrightPlus => "bright+",
+            ButtonId::MetronomeBrightMinus => "bright-",
+            ButtonId::Next => "next",
+            ButtonId::Previous => "prev",
+            ButtonId::Stop => "stop",
+            ButtonId::Start => "start",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ButtonId> {
+        // The console has always taken the long form too
+        if name == "previous" {
+            return Some(ButtonId::Previous);
+        }
+        ButtonId::ALL.into_iter().find(|id| id.name() == name)
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct ButtonEvent {
     pub id: ButtonId,
@@ -35,6 +80,24 @@ pub enum Mode {
     Menu,
     Main,
     Lock,
+    Diagnostics,
+    Network,
+    Timers,
+    Events,
+    About,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Clone, Copy)]
+pub struct Notification {
+    pub priority: Priority,
+    pub msg: StaticString<32>,
 }
 
 #[derive(Clone, Copy)]
@@ -42,10 +105,12 @@ pub enum Action {
     NextItem,
     PreviousItem,
     SelectItem,
+    ResetItem,
     Character(u8),
     NextCue,
     PreviousCue,
     ModeChange(Mode),
+    OpenMenu(MenuPage),
     TextEntryStart {
         ctx: TextEntryContext,
         initial_value: StaticString<32>,
@@ -70,9 +135,12 @@ pub enum Action {
     DebugMessage {
         msg: StaticString<32>,
     },
+    Notify(Notification),
     ReloadConnection,
+    SendDiagnostics,
     GainConnection,
     LoseConnection,
+    LinkStatusChanged(LinkStatus),
     MessageFromCore(SmallMessage),
     RequestToCore(Request),
     LEDSet(LED, bool),
@@ -83,4 +151,12 @@ pub enum Action {
     MetronomeStop,
     MetronomeStart,
     MetronomeTempoTap,
+    MetronomeBeat(u8),
+    TimerStartStop(TimerId),
+    TimerReset(TimerId),
+    /// Act on whichever timer the main screen shows.
+    FeaturedTimerStartStop,
+    FeaturedTimerReset,
+    /// Flash every LED in the alarm pattern, or stop and show their state again.
+    TimerAlarm(bool),
 }
@@ -0,0 +1,222 @@
+use crate::events::Priority;
+use crate::http;
+use crate::logger::{self, Level};
+use crate::netutil;
+use crate::settings::SharedFlash;
+use crate::ui;
+use common::mem::str::StaticString;
+use embassy_boot_rp::{
+    AlignedBuffer, BlockingFirmwareState, BlockingFirmwareUpdater, FirmwareUpdaterConfig,
+};
+use embassy_net::tcp::TcpSocket;
+use embassy_net::Stack;
+use embassy_rp::flash::ERASE_SIZE;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Timer};
+use embedded_storage::nor_flash::ReadNorFlash;
+
+const FIRMWARE_PORT: u16 = 3232;
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Image header: magic, then length and CRC-32 of the image, both little endian, then the
+/// web PIN padded out with zeros.
+const HEADER_MAGIC: [u8; 4] = *b"CKFW";
+const HEADER_LEN: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum UpdateError {
+    BadHeader,
+    WrongPin,
+    TooLarge,
+    Disconnected,
+    Flash,
+    Checksum,
+}
+
+impl UpdateError {
+    fn label(&self) -> &'static str {
+        match self {
+            UpdateError::BadHeader => "bad header",
+            UpdateError::WrongPin => "wrong PIN",
+            UpdateError::TooLarge => "image too large",
+            UpdateError::Disconnected => "disconnected",
+            UpdateError::Flash => "flash error",
+            UpdateError::Checksum => "checksum mismatch",
+        }
+    }
+}
+
+/// CRC-32 (IEEE), the same as zlib and `crc32` on the command line.
+fn crc32(mut crc: u32, data: &[u8]) -> u32 {
+    crc = !crc;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Set once we have subscribed to a core, the proof a new image works.
+static CONNECTED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+pub fn confirm_boot() {
+    CONNECTED.signal(());
+}
+
+/// Waits for the first subscription to a core, then trusts the running image. Resetting
+/// any earlier, watchdog included, has the bootloader roll back to the previous one.
+pub async fn confirm_when_connected(flash: &SharedFlash) {
+    CONNECTED.wait().await;
+    mark_booted(flash);
+}
+
+/// Tells the bootloader the running image is good, so it stays after the next reset.
+fn mark_booted(flash: &SharedFlash) {
+    let config = FirmwareUpdaterConfig::from_linkerfile_blocking(flash, flash);
+    let mut aligned = AlignedBuffer([0; 1]);
+    let mut state = BlockingFirmwareState::from_config(config, &mut aligned.0);
+    if let Err(err) = state.mark_booted() {
+        logger::log(
+            Level::Error,
+            "firmware",
+            format_args!("mark booted failed: {:?}", err),
+        );
+    }
+}
+
+#[embassy_executor::task]
+pub async fn firmware_task(stack: Stack<'static>, flash: &'static SharedFlash) {
+    let mut rx_buffer = [0; 2048];
+    let mut tx_buffer = [0; 128];
+
+    loop {
+        let mut socket = TcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer);
+        socket.set_timeout(Some(TIMEOUT));
+        if socket.accept(FIRMWARE_PORT).await.is_err() {
+            continue;
+        }
+        match receive(&mut socket, flash).await {
+            Ok(len) => {
+                logger::log(
+                    Level::Info,
+                    "firmware",
+                    format_args!("{} byte image verified, restarting", len),
+                );
+                ui::notify(Priority::Critical, "Update ok, restarting");
+                let _ = netutil::write_all(&mut socket, b"OK\n").await;
+                socket.close();
+                let _ = socket.flush().await;
+                // Give the reply and the notification a moment to get out
+                Timer::after_secs(2).await;
+                cortex_m::peripheral::SCB::sys_reset();
+            }
+            Err(err) => {
+                logger::log(
+                    Level::Error,
+                    "firmware",
+                    format_args!("update failed: {}", err.label()),
+                );
+                ui::notify(Priority::Warning, "Update failed");
+                let mut buf = [0u8; 32];
+                let reply = format_no_std::show(&mut buf, format_args!("ERR {}\n", err.label()))
+                    .unwrap_or_default();
+                let _ = netutil::write_all(&mut socket, reply.as_bytes()).await;
+            }
+        }
+        socket.close();
+        let _ = socket.flush().await;
+    }
+}
+
+async fn read_exact(socket: &mut TcpSocket<'_>, buf: &mut [u8]) -> Result<(), UpdateError> {
+    let mut len = 0;
+    while len < buf.len() {
+        match socket.read(&mut buf[len..]).await {
+            Ok(0) | Err(_) => return Err(UpdateError::Disconnected),
+            Ok(n) => len += n,
+        }
+    }
+    Ok(())
+}
+
+/// Streams an image into the DFU slot a sector at a time, then reads it back to check it
+/// landed intact before asking the bootloader to swap it in.
+async fn receive(socket: &mut TcpSocket<'_>, flash: &SharedFlash) -> Result<usize, UpdateError> {
+    let mut header = [0u8; HEADER_LEN];
+    read_exact(socket, &mut header).await?;
+    if header[..4] != HEADER_MAGIC {
+        return Err(UpdateError::BadHeader);
+    }
+    let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    let crc = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+    // Same rule as settings over HTTP: no PIN set on the unit, no updates
+    let pin = &header[12..];
+    let pin_len = pin.iter().position(|b| *b == 0).unwrap_or(pin.len());
+    let pin = core::str::from_utf8(&pin[..pin_len]).map_err(|_| UpdateError::BadHeader)?;
+    if !http::pin_ok(Some(StaticString::new(pin))).await {
+        return Err(UpdateError::WrongPin);
+    }
+
+    let config = FirmwareUpdaterConfig::from_linkerfile_blocking(flash, flash);
+    // The DFU partition holds one sector more than the active one, for the swap
+    let active_size = config.dfu.size() as usize - ERASE_SIZE;
+    if len == 0 || len > active_size {
+        return Err(UpdateError::TooLarge);
+    }
+    let mut aligned = AlignedBuffer([0; 1]);
+    let mut updater = BlockingFirmwareUpdater::new(config, &mut aligned.0);
+
+    logger::log(
+        Level::Info,
+        "firmware",
+        format_args!("receiving {} byte image", len),
+    );
+    ui::notify(Priority::Info, "Updating firmware");
+
+    let mut sector = [0u8; ERASE_SIZE];
+    let mut offset = 0;
+    let mut shown = 0;
+    while offset < len {
+        let n = (len - offset).min(ERASE_SIZE);
+        read_exact(socket, &mut sector[..n]).await?;
+        sector[n..].fill(0xFF);
+        // Erases and writes one sector, short enough to keep the watchdog fed
+        updater
+            .write_firmware(offset, &sector)
+            .map_err(|_| UpdateError::Flash)?;
+        offset += n;
+
+        let percent = offset * 100 / len;
+        if percent / 10 > shown {
+            shown = percent / 10;
+            let mut buf = [0u8; 32];
+            let msg = format_no_std::show(&mut buf, format_args!("Update {}%", percent))
+                .unwrap_or_default();
+            ui::notify(Priority::Info, msg);
+        }
+    }
+
+    let mut check = FirmwareUpdaterConfig::from_linkerfile_blocking(flash, flash).dfu;
+    let mut stored = 0;
+    let mut offset = 0;
+    while offset < len {
+        let n = (len - offset).min(ERASE_SIZE);
+        check
+            .read(offset as u32, &mut sector[..n])
+            .map_err(|_| UpdateError::Flash)?;
+        stored = crc32(stored, &sector[..n]);
+        offset += n;
+    }
+    if stored != crc {
+        return Err(UpdateError::Checksum);
+    }
+
+    updater.mark_updated().map_err(|_| UpdateError::Flash)?;
+    Ok(len)
+}
@@ -1,26 +1,30 @@
 use crate::ui::{debug, debug_now};
 use bitflags::bitflags;
+use common::mem::str::StaticString;
+use core::cell::RefCell;
+use core::convert::Infallible;
 use embassy_rp::{
     i2c::{Async, I2c},
     peripherals::I2C1,
 };
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use embassy_time::Duration;
 use embedded_graphics::{
+    draw_target::DrawTargetExt,
     image::{Image, ImageRaw},
     mono_font::{
         ascii::{FONT_10X20, FONT_6X10},
         MonoTextStyle, MonoTextStyleBuilder,
     },
     pixelcolor::BinaryColor,
-    prelude::Point,
+    prelude::{DrawTarget, OriginDimensions, Point},
     primitives::{PrimitiveStyleBuilder, Rectangle, StyledDrawable},
     text::{Text, TextStyle, TextStyleBuilder},
 };
-use embedded_graphics::{prelude::Size, Drawable};
-use ssd1306::prelude::I2CInterface;
-use ssd1306::{
-    mode::{BufferedGraphicsMode, DisplayConfig},
-    size::DisplaySize128x64,
-};
+use embedded_graphics::{prelude::Size, Drawable, Pixel};
+use portable_atomic::{AtomicU8, Ordering};
+use ssd1306::prelude::{Brightness, DisplayRotation, I2CInterface};
+use ssd1306::{command::AddrMode, mode::BasicMode, size::DisplaySize128x64};
 
 bitflags! {
     #[derive(PartialEq, Clone)]
@@ -39,14 +43,356 @@ bitflags! {
 
 type I2CType = I2c<'static, I2C1, Async>;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScrollSpeed {
+    Off,
+    Slow,
+    Medium,
+    Fast,
+}
+
+impl ScrollSpeed {
+    pub const ALL: [ScrollSpeed; 4] = [
+        ScrollSpeed::Off,
+        ScrollSpeed::Slow,
+        ScrollSpeed::Medium,
+        ScrollSpeed::Fast,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ScrollSpeed::Off => "Off",
+            ScrollSpeed::Slow => "Slow",
+            ScrollSpeed::Medium => "Medium",
+            ScrollSpeed::Fast => "Fast",
+        }
+    }
+
+    /// Pixels moved per marquee tick.
+    fn step(&self) -> u32 {
+        match self {
+            ScrollSpeed::Off => 0,
+            ScrollSpeed::Slow => 1,
+            ScrollSpeed::Medium => 2,
+            ScrollSpeed::Fast => 4,
+        }
+    }
+
+    pub fn next(&self) -> ScrollSpeed {
+        ScrollSpeed::ALL[(*self as usize + 1) % ScrollSpeed::ALL.len()]
+    }
+}
+
+static SCROLL_SPEED: AtomicU8 = AtomicU8::new(ScrollSpeed::Slow as u8);
+
+pub fn scroll_speed() -> ScrollSpeed {
+    ScrollSpeed::ALL[SCROLL_SPEED.load(Ordering::Relaxed) as usize]
+}
+
+pub fn set_scroll_speed(speed: ScrollSpeed) {
+    SCROLL_SPEED.store(speed as u8, Ordering::Relaxed);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Contrast {
+    Dimmest,
+    Dim,
+    Normal,
+    Bright,
+    Brightest,
+}
+
+impl Contrast {
+    pub const ALL: [Contrast; 5] = [
+        Contrast::Dimmest,
+        Contrast::Dim,
+        Contrast::Normal,
+        Contrast::Bright,
+        Contrast::Brightest,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Contrast::Dimmest => "Dimmest",
+            Contrast::Dim => "Dim",
+            Contrast::Normal => "Normal",
+            Contrast::Bright => "Bright",
+            Contrast::Brightest => "Brightest",
+        }
+    }
+
+    fn brightness(&self) -> Brightness {
+        match self {
+            Contrast::Dimmest => Brightness::DIMMEST,
+            Contrast::Dim => Brightness::DIM,
+            Contrast::Normal => Brightness::NORMAL,
+            Contrast::Bright => Brightness::BRIGHT,
+            Contrast::Brightest => Brightness::BRIGHTEST,
+        }
+    }
+
+    pub fn next(&self) -> Contrast {
+        Contrast::ALL[(*self as usize + 1) % Contrast::ALL.len()]
+    }
+}
+
+static CONTRAST: AtomicU8 = AtomicU8::new(Contrast::Normal as u8);
+
+pub fn contrast() -> Contrast {
+    Contrast::ALL[CONTRAST.load(Ordering::Relaxed) as usize]
+}
+
+pub fn set_contrast(contrast: Contrast) {
+    CONTRAST.store(contrast as u8, Ordering::Relaxed);
+}
+
+/// One bit per pixel, rows of 128 left to right.
+pub const LOGO_LEN: usize = 128 * 64 / 8;
+
+static CUSTOM_LOGO: CriticalSectionMutex<RefCell<Option<[u8; LOGO_LEN]>>> =
+    CriticalSectionMutex::new(RefCell::new(None));
+
+/// Shows `logo` instead of the built-in one, until set back to `None`.
+pub fn set_custom_logo(logo: Option<[u8; LOGO_LEN]>) {
+    CUSTOM_LOGO.lock(|l| *l.borrow_mut() = logo);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    Normal,
+    Flipped,
+}
+
+impl Orientation {
+    pub const ALL: [Orientation; 2] = [Orientation::Normal, Orientation::Flipped];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Orientation::Normal => "Normal",
+            Orientation::Flipped => "Flipped",
+        }
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        match self {
+            Orientation::Normal => DisplayRotation::Rotate0,
+            Orientation::Flipped => DisplayRotation::Rotate180,
+        }
+    }
+
+    pub fn next(&self) -> Orientation {
+        Orientation::ALL[(*self as usize + 1) % Orientation::ALL.len()]
+    }
+}
+
+static ORIENTATION: AtomicU8 = AtomicU8::new(Orientation::Normal as u8);
+
+pub fn orientation() -> Orientation {
+    Orientation::ALL[ORIENTATION.load(Ordering::Relaxed) as usize]
+}
+
+pub fn set_orientation(orientation: Orientation) {
+    ORIENTATION.store(orientation as u8, Ordering::Relaxed);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdleTimeout {
+    Never,
+    Sec30,
+    Min2,
+    Min10,
+}
+
+impl IdleTimeout {
+    pub const ALL: [IdleTimeout; 4] = [
+        IdleTimeout::Never,
+        IdleTimeout::Sec30,
+        IdleTimeout::Min2,
+        IdleTimeout::Min10,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            IdleTimeout::Never => "Never",
+            IdleTimeout::Sec30 => "30 s",
+            IdleTimeout::Min2 => "2 min",
+            IdleTimeout::Min10 => "10 min",
+        }
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        match self {
+            IdleTimeout::Never => None,
+            IdleTimeout::Sec30 => Some(Duration::from_secs(30)),
+            IdleTimeout::Min2 => Some(Duration::from_secs(120)),
+            IdleTimeout::Min10 => Some(Duration::from_secs(600)),
+        }
+    }
+
+    pub fn next(&self) -> IdleTimeout {
+        IdleTimeout::ALL[(*self as usize + 1) % IdleTimeout::ALL.len()]
+    }
+}
+
+static IDLE_TIMEOUT: AtomicU8 = AtomicU8::new(IdleTimeout::Min2 as u8);
+
+pub fn idle_timeout() -> IdleTimeout {
+    IdleTimeout::ALL[IDLE_TIMEOUT.load(Ordering::Relaxed) as usize]
+}
+
+pub fn set_idle_timeout(timeout: IdleTimeout) {
+    IDLE_TIMEOUT.store(timeout as u8, Ordering::Relaxed);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarqueeSlot {
+    Cue,
+    MenuValue,
+    About,
+}
+
+impl MarqueeSlot {
+    const COUNT: usize = 3;
+}
+
+/// Longest text a marquee scrolls through, anything past it is cut off.
+const MARQUEE_LEN: usize = 64;
+
+/// Ticks to rest at either end before scrolling on.
+const MARQUEE_HOLD: u8 = 20;
+
+/// Text that scrolls sideways through its strip when it doesn't fit.
+#[derive(Clone, Copy)]
+struct Marquee {
+    text: StaticString<MARQUEE_LEN>,
+    origin: Point,
+    font: FontData<'static>,
+    len: usize,
+    offset: u32,
+    hold: u8,
+    active: bool,
+}
+
+impl Marquee {
+    fn overflow(&self) -> u32 {
+        (self.text.len() as u32 * self.font.width).saturating_sub(self.len as u32 * self.font.width)
+    }
+
+    fn scrolls(&self) -> bool {
+        self.active && self.overflow() > 0
+    }
+
+    /// Returns true if the text moved.
+    fn tick(&mut self, step: u32) -> bool {
+        if !self.scrolls() || step == 0 {
+            return false;
+        }
+        if self.hold > 0 {
+            self.hold -= 1;
+            return false;
+        }
+        if self.offset >= self.overflow() {
+            self.offset = 0;
+        } else {
+            self.offset = (self.offset + step).min(self.overflow());
+        }
+        if self.offset == 0 || self.offset == self.overflow() {
+            self.hold = MARQUEE_HOLD;
+        }
+        true
+    }
+}
+
+const WIDTH: usize = 128;
+const PAGES: usize = 8;
+
+/// Local copy of the display RAM, tracking which columns of each 8 pixel page have changed
+/// since they were last sent.
+struct FrameBuffer {
+    pixels: [u8; WIDTH * PAGES],
+    sent: [u8; WIDTH * PAGES],
+    dirty: [Option<(usize, usize)>; PAGES],
+}
+
+impl FrameBuffer {
+    const fn new() -> Self {
+        Self {
+            pixels: [0; WIDTH * PAGES],
+            // Unknown display contents, make sure the first flush sends everything
+            sent: [0xAA; WIDTH * PAGES],
+            dirty: [Some((0, WIDTH - 1)); PAGES],
+        }
+    }
+
+    fn set_pixel(&mut self, x: u32, y: u32, on: bool) {
+        let (x, y) = (x as usize, y as usize);
+        if x >= WIDTH || y >= PAGES * 8 {
+            return;
+        }
+        let page = y / 8;
+        let byte = &mut self.pixels[page * WIDTH + x];
+        let new = *byte & !(1 << (y % 8)) | ((on as u8) << (y % 8));
+        if new != *byte {
+            *byte = new;
+            self.dirty[page] = Some(match self.dirty[page] {
+                Some((min, max)) => (min.min(x), max.max(x)),
+                None => (x, x),
+            });
+        }
+    }
+
+    fn fill(&mut self, value: u8) {
+        self.pixels.fill(value);
+        self.dirty = [Some((0, WIDTH - 1)); PAGES];
+    }
+
+    /// Forgets what the display holds, so the next flush sends every byte.
+    fn invalidate(&mut self) {
+        for (sent, px) in self.sent.iter_mut().zip(self.pixels.iter()) {
+            *sent = !*px;
+        }
+        self.dirty = [Some((0, WIDTH - 1)); PAGES];
+    }
+}
+
+impl OriginDimensions for FrameBuffer {
+    fn size(&self) -> Size {
+        Size::new(WIDTH as u32, PAGES as u32 * 8)
+    }
+}
+
+impl DrawTarget for FrameBuffer {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if point.x >= 0 && point.y >= 0 {
+                self.set_pixel(point.x as u32, point.y as u32, color.is_on());
+            }
+        }
+        Ok(())
+    }
+}
+
 pub struct GraphicsController {
-    display: ssd1306::Ssd1306<
-        I2CInterface<I2CType>,
-        DisplaySize128x64,
-        BufferedGraphicsMode<DisplaySize128x64>,
-    >,
+    display: ssd1306::Ssd1306Async<I2CInterface<I2CType>, DisplaySize128x64, BasicMode>,
+    frame: FrameBuffer,
+    banner: Option<StaticString<32>>,
+    // What the screen looks like underneath the banner, kept up to date while it is shown
+    banner_shadow: [u8; Self::BANNER_SHADOW_LEN],
+    marquees: [Option<Marquee>; MarqueeSlot::COUNT],
+    // Moves everything but the banner, to spread wear on static layouts
+    offset: Point,
+    dimmed: bool,
+    contrast_sent: Option<Contrast>,
+    orientation_sent: Orientation,
 }
 
+#[derive(Clone, Copy)]
 pub struct FontData<'a> {
     pub width: u32,
     pub height: u32,
@@ -54,7 +400,7 @@ pub struct FontData<'a> {
 }
 
 impl GraphicsController {
-    const LOGO_DATA: &[u8; 1024] = include_bytes!("../logo.bin");
+    const LOGO_DATA: &[u8; LOGO_LEN] = include_bytes!("../logo.bin");
     pub const CHAR_LARGE: FontData<'_> = FontData {
         width: 10,
         height: 20,
@@ -77,21 +423,50 @@ impl GraphicsController {
         .baseline(embedded_graphics::text::Baseline::Top)
         .build();
 
+    pub const BEAT_GRID_SLOTS: u32 = 12;
+
+    pub const BANNER_ORIGIN: Point = Point::new(0, 52);
+    pub const BANNER_SIZE: Size = Size::new(128, 12);
+    const BANNER_SHADOW_LEN: usize = (128 * 12) / 8;
+
     pub const TR_ALIGN: TextStyle = TextStyleBuilder::new()
         .alignment(embedded_graphics::text::Alignment::Right)
         .baseline(embedded_graphics::text::Baseline::Top)
         .build();
 
-    pub fn new(i2c: I2CType) -> Self {
+    pub async fn new(i2c: I2CType) -> Self {
         let interface = ssd1306::I2CDisplayInterface::new(i2c);
-        let mut display = ssd1306::Ssd1306::new(
+        let orientation = orientation();
+        let mut display = ssd1306::Ssd1306Async::new(
             interface,
             ssd1306::size::DisplaySize128x64,
-            ssd1306::rotation::DisplayRotation::Rotate0,
-        )
-        .into_buffered_graphics_mode();
-        display.init().unwrap();
-        Self { display }
+            orientation.rotation(),
+        );
+        display
+            .init_with_addr_mode(AddrMode::Horizontal)
+            .await
+            .unwrap();
+        Self {
+            display,
+            frame: FrameBuffer::new(),
+            banner: None,
+            banner_shadow: [0; Self::BANNER_SHADOW_LEN],
+            marquees: [None; MarqueeSlot::COUNT],
+            offset: Point::zero(),
+            dimmed: false,
+            contrast_sent: None,
+            orientation_sent: orientation,
+        }
+    }
+
+    /// Shifts subsequent drawing by `offset`. Takes effect on the next `clear` and redraw.
+    pub fn set_offset(&mut self, offset: Point) {
+        self.offset = offset;
+    }
+
+    /// Drops to the lowest contrast until undimmed, applied on the next `commit`.
+    pub fn set_dimmed(&mut self, dimmed: bool) {
+        self.dimmed = dimmed;
     }
 
     pub fn text_strip(
@@ -103,7 +478,89 @@ impl GraphicsController {
         align: TextStyle,
     ) {
         self.bounded_clear(origin, Size::new(len as u32 * font.width, font.height));
-        Text::with_text_style(s, origin, font.style, align).draw(&mut self.display);
+        Text::with_text_style(s, origin, font.style, align).draw(self);
+    }
+
+    /// Like `text_strip`, but text longer than `len` scrolls through the strip on each
+    /// `scroll_marquees`. Setting the same text again keeps its scroll position.
+    pub fn marquee(
+        &mut self,
+        slot: MarqueeSlot,
+        s: &str,
+        origin: Point,
+        font: FontData<'static>,
+        len: usize,
+    ) {
+        let text = StaticString::new(s);
+        let m = match self.marquees[slot as usize] {
+            Some(mut m) if m.text == text && m.origin == origin && m.len == len => {
+                m.active = true;
+                m
+            }
+            _ => Marquee {
+                text,
+                origin,
+                font,
+                len,
+                offset: 0,
+                hold: MARQUEE_HOLD,
+                active: true,
+            },
+        };
+        self.marquees[slot as usize] = Some(m);
+        self.draw_marquee(&m);
+    }
+
+    /// Whether any marquee on screen has text to scroll, so `scroll_marquees` is worth calling.
+    pub fn scrolling(&self) -> bool {
+        scroll_speed().step() > 0 && self.marquees.iter().flatten().any(Marquee::scrolls)
+    }
+
+    /// Advances every visible marquee. Returns true if anything needs committing.
+    pub fn scroll_marquees(&mut self) -> bool {
+        let step = scroll_speed().step();
+        let mut moved = false;
+        for i in 0..MarqueeSlot::COUNT {
+            let Some(mut m) = self.marquees[i] else {
+                continue;
+            };
+            if m.tick(step) {
+                self.draw_marquee(&m);
+                moved = true;
+            }
+            self.marquees[i] = Some(m);
+        }
+        moved
+    }
+
+    fn draw_marquee(&mut self, m: &Marquee) {
+        let area = Rectangle::new(
+            m.origin,
+            Size::new(m.len as u32 * m.font.width, m.font.height),
+        );
+        self.bounded_clear(area.top_left, area.size);
+        Text::with_text_style(
+            m.text.str(),
+            m.origin - Point::new(m.offset as i32, 0),
+            m.font.style,
+            Self::TL_ALIGN,
+        )
+        .draw(&mut self.clipped(&area));
+    }
+
+    /// Large font text at twice its size, `len` characters wide.
+    pub fn text_giant(&mut self, s: &str, origin: Point, len: usize) {
+        let font = Self::CHAR_LARGE;
+        self.bounded_clear(
+            origin,
+            Size::new(len as u32 * font.width * 2, font.height * 2),
+        );
+        let mut scaled = Scaled {
+            target: self,
+            origin,
+            factor: 2,
+        };
+        Text::with_text_style(s, origin, font.style, Self::TL_ALIGN).draw(&mut scaled);
     }
 
     pub fn bounded_clear(&mut self, origin: Point, size: Size) {
@@ -132,7 +589,7 @@ impl GraphicsController {
                 .stroke_alignment(embedded_graphics::primitives::StrokeAlignment::Inside);
         }
 
-        &Rectangle::new(origin, size).draw_styled(&style.build(), &mut self.display);
+        &Rectangle::new(origin, size).draw_styled(&style.build(), self);
     }
 
     pub fn x6_dot(&mut self, origin: Point, width: u32) {
@@ -146,6 +603,21 @@ impl GraphicsController {
         );
     }
 
+    /// Up to 12 beats in rows of 4, the current one drawn large. Slots past `total` are blank.
+    pub fn beat_grid(&mut self, origin: Point, current: u8, total: u8) {
+        for i in 0..Self::BEAT_GRID_SLOTS {
+            let pos = origin + Size::new((i % 4) * 6, (i / 4) * 6);
+            let width = if i >= total as u32 {
+                0
+            } else if i + 1 == current as u32 {
+                4
+            } else {
+                2
+            };
+            self.x6_dot(pos, width);
+        }
+    }
+
     pub fn list_item(&mut self, label: &str, value: Option<&str>, origin: Point, highlight: bool) {
         //self.rect(origin, Size::new(120, 14), Some(BinaryColor::On), None);
         //self.rect(
@@ -182,16 +654,129 @@ impl GraphicsController {
     }
 
     pub fn logo(&mut self) {
-        let raw_image = ImageRaw::<BinaryColor>::new(Self::LOGO_DATA, 128);
-        Image::new(&raw_image, Point::zero()).draw(&mut self.display);
+        let data = CUSTOM_LOGO
+            .lock(|l| *l.borrow())
+            .unwrap_or(*Self::LOGO_DATA);
+        let raw_image = ImageRaw::<BinaryColor>::new(&data, 128);
+        Image::new(&raw_image, Point::zero()).draw(self);
+    }
+
+    /// Overlays a notification banner on the bottom of the screen. Drawing continues as normal
+    /// underneath it, and is revealed again by `hide_banner`.
+    pub fn show_banner(&mut self, text: &str) {
+        self.banner = Some(StaticString::new(text));
+    }
+
+    pub fn hide_banner(&mut self) {
+        if self.banner.take().is_none() {
+            return;
+        }
+        for y in 0..Self::BANNER_SIZE.height {
+            for x in 0..Self::BANNER_SIZE.width {
+                let on = self.banner_shadow[Self::shadow_idx(x, y)] & (1 << (x % 8)) != 0;
+                self.frame
+                    .set_pixel(x, Self::BANNER_ORIGIN.y as u32 + y, on);
+            }
+        }
+    }
+
+    fn draw_banner(&mut self, text: &str) {
+        let style = PrimitiveStyleBuilder::new()
+            .fill_color(BinaryColor::On)
+            .build();
+        Rectangle::new(Self::BANNER_ORIGIN, Self::BANNER_SIZE).draw_styled(&style, &mut self.frame);
+        let inverted = MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(BinaryColor::Off)
+            .build();
+        Text::with_text_style(
+            text,
+            Self::BANNER_ORIGIN + Size::new(2, 1),
+            inverted,
+            Self::TL_ALIGN,
+        )
+        .draw(&mut self.frame);
+    }
+
+    fn shadow_idx(x: u32, y: u32) -> usize {
+        (y * Self::BANNER_SIZE.width / 8 + x / 8) as usize
     }
 
-    pub fn commit(&mut self) {
-        self.display.flush();
+    /// Sends the changed part of each page to the display. Pages are only touched where their
+    /// bytes differ from what was last sent, so redrawing an unchanged value costs nothing.
+    pub async fn commit(&mut self) {
+        if let Some(text) = self.banner {
+            self.draw_banner(text.str());
+        }
+        let contrast = if self.dimmed {
+            Contrast::Dimmest
+        } else {
+            contrast()
+        };
+        if self.contrast_sent != Some(contrast)
+            && self
+                .display
+                .set_brightness(contrast.brightness())
+                .await
+                .is_ok()
+        {
+            self.contrast_sent = Some(contrast);
+        }
+        let orientation = orientation();
+        if self.orientation_sent != orientation
+            && self
+                .display
+                .set_rotation(orientation.rotation())
+                .await
+                .is_ok()
+        {
+            self.orientation_sent = orientation;
+            // Column remapping only applies to data written after it
+            self.frame.invalidate();
+        }
+        for page in 0..PAGES {
+            let Some((min, max)) = self.frame.dirty[page].take() else {
+                continue;
+            };
+            let row = page * WIDTH;
+            let differs = |x: &usize| self.frame.pixels[row + x] != self.frame.sent[row + x];
+            let (Some(first), Some(last)) =
+                ((min..=max).find(differs), (min..=max).rev().find(differs))
+            else {
+                continue;
+            };
+            let y = (page * 8) as u8;
+            if self
+                .display
+                .set_draw_area((first as u8, y), (last as u8 + 1, y + 8))
+                .await
+                .is_err()
+            {
+                // Leave it marked, the next commit will retry
+                self.frame.dirty[page] = Some((min, max));
+                continue;
+            }
+            let span = row + first..=row + last;
+            if self
+                .display
+                .draw(&self.frame.pixels[span.clone()])
+                .await
+                .is_ok()
+            {
+                self.frame.sent[span.clone()].copy_from_slice(&self.frame.pixels[span]);
+            } else {
+                self.frame.dirty[page] = Some((min, max));
+            }
+        }
     }
 
+    /// Blanks the screen. Marquees stop until they are drawn again.
     pub fn clear(&mut self) {
-        self.display.clear_buffer();
+        self.frame.fill(0);
+        self.banner_shadow.fill(0);
+        for m in self.marquees.iter_mut().flatten() {
+            m.active = false;
+        }
     }
 
     //pub async fn redraw_screen_element(&mut self, element: ScreenElement) {
@@ -379,3 +964,70 @@ impl GraphicsController {
     //    //pin_led_vlt.set_high();
     //}
 }
+
+impl OriginDimensions for GraphicsController {
+    fn size(&self) -> Size {
+        self.frame.size()
+    }
+}
+
+/// Blows up everything drawn to it by `factor` around `origin`.
+struct Scaled<'a, D> {
+    target: &'a mut D,
+    origin: Point,
+    factor: i32,
+}
+
+impl<D: OriginDimensions> OriginDimensions for Scaled<'_, D> {
+    fn size(&self) -> Size {
+        self.target.size()
+    }
+}
+
+impl<D: DrawTarget<Color = BinaryColor>> DrawTarget for Scaled<'_, D> {
+    type Color = BinaryColor;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let (origin, f) = (self.origin, self.factor);
+        self.target
+            .draw_iter(pixels.into_iter().flat_map(move |Pixel(point, color)| {
+                let base = origin + (point - origin) * f;
+                (0..f * f).map(move |i| Pixel(base + Point::new(i % f, i / f), color))
+            }))
+    }
+}
+
+impl DrawTarget for GraphicsController {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let banner_top = Self::BANNER_ORIGIN.y;
+        for Pixel(point, color) in pixels {
+            let point = point + self.offset;
+            if point.x < 0 || point.y < 0 {
+                continue;
+            }
+            let (x, y) = (point.x as u32, point.y as u32);
+            let on = color.is_on();
+            if point.y >= banner_top && x < Self::BANNER_SIZE.width {
+                let idx = Self::shadow_idx(x, y - banner_top as u32);
+                if let Some(byte) = self.banner_shadow.get_mut(idx) {
+                    *byte = *byte & !(1 << (x % 8)) | ((on as u8) << (x % 8));
+                }
+                if self.banner.is_some() {
+                    continue;
+                }
+            }
+            self.frame.set_pixel(x, y, on);
+        }
+        Ok(())
+    }
+}
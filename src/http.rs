@@ -0,0 +1,632 @@
+use crate::diagnostics;
+use crate::events::{Action, ButtonId, Priority};
+use crate::graphics::LOGO_LEN;
+use crate::led;
+use crate::logger::{self, Level};
+use crate::netutil;
+use crate::network2;
+use crate::settings;
+use crate::state::{Core, NetMode, SystemState, MAX_CORES};
+use crate::{ui, ACTION_UPSTREAM, STATE};
+use common::mem::network::IpAddress;
+use common::mem::str::StaticString;
+use core::fmt::{self, Write};
+use core::net::Ipv4Addr;
+use embassy_net::tcp::TcpSocket;
+use embassy_net::Stack;
+use embassy_time::{Duration, Instant};
+
+const HTTP_PORT: u16 = 80;
+const TIMEOUT: Duration = Duration::from_secs(5);
+const PAGE_LEN: usize = 4096;
+// Room for the headers and a logo upload
+const REQUEST_LEN: usize = 1024 + LOGO_LEN;
+
+/// Fixed size buffer the responses are formatted into.
+struct Page {
+    buf: [u8; PAGE_LEN],
+    len: usize,
+}
+
+impl Page {
+    fn new() -> Self {
+        Self {
+            buf: [0; PAGE_LEN],
+            len: 0,
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl Write for Page {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > PAGE_LEN {
+            return Err(fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Text with the characters HTML gives meaning to escaped.
+struct Escaped<'a>(&'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '<' => f.write_str("&lt;")?,
+                '>' => f.write_str("&gt;")?,
+                '&' => f.write_str("&amp;")?,
+                '"' => f.write_str("&quot;")?,
+                _ => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Octets([u8; 4]);
+
+impl fmt::Display for Octets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d] = self.0;
+        write!(f, "{}.{}.{}.{}", a, b, c, d)
+    }
+}
+
+/// An address for a form field, empty while it is 0.0.0.0 and so unused.
+struct OptionalAddress([u8; 4]);
+
+impl fmt::Display for OptionalAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 != [0, 0, 0, 0] {
+            write!(f, "{}", Octets(self.0))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The configured cores as options of the master select.
+struct MasterOptions<'a>(&'a SystemState);
+
+impl fmt::Display for MasterOptions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let master = network2::master_core();
+        for (slot, core) in self.0.cores.iter().enumerate() {
+            if core.configured() {
+                let selected = if slot == master { " selected" } else { "" };
+                let role = Core::role(slot);
+                write!(f, "<option value={role}{selected}>{role}</option>")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[embassy_executor::task]
+pub async fn http_task(stack: Stack<'static>) {
+    let mut rx_buffer = [0; 1024];
+    let mut tx_buffer = [0; 2048];
+    let mut request = [0u8; REQUEST_LEN];
+
+    loop {
+        let mut socket = TcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer);
+        socket.set_timeout(Some(TIMEOUT));
+        if socket.accept(HTTP_PORT).await.is_err() {
+            continue;
+        }
+        let mut page = Page::new();
+        match read_request(&mut socket, &mut request).await {
+            Ok(n) => handle(stack, &request[..n], &mut page).await,
+            Err(ReadError::TooLarge) => {
+                logger::log(Level::Warn, "http", format_args!("request too large"));
+                let _ = page.write_str(
+                    "HTTP/1.1 413 Payload Too Large\r\nConnection: close\r\n\r\nToo large\r\n",
+                );
+            }
+            Err(ReadError::Disconnected) => {}
+        }
+        if netutil::write_all(&mut socket, page.as_bytes())
+            .await
+            .is_err()
+        {
+            logger::log(Level::Debug, "http", format_args!("client went away"));
+        }
+        socket.close();
+        let _ = socket.flush().await;
+    }
+}
+
+enum ReadError {
+    TooLarge,
+    Disconnected,
+}
+
+async fn read_some(socket: &mut TcpSocket<'_>, buf: &mut [u8]) -> Result<usize, ReadError> {
+    match socket.read(buf).await {
+        Ok(0) | Err(_) => Err(ReadError::Disconnected),
+        Ok(n) => Ok(n),
+    }
+}
+
+/// Reads the headers and, if there is one, the body.
+async fn read_request(socket: &mut TcpSocket<'_>, buf: &mut [u8]) -> Result<usize, ReadError> {
+    let mut len = 0;
+    loop {
+        len += read_some(socket, &mut buf[len..]).await?;
+        let Some(end) = buf[..len].windows(4).position(|w| w == b"\r\n\r\n") else {
+            if len == buf.len() {
+                return Err(ReadError::TooLarge);
+            }
+            continue;
+        };
+        let want = end + 4 + content_length(&buf[..end]);
+        if want > buf.len() {
+            return Err(ReadError::TooLarge);
+        }
+        while len < want {
+            len += read_some(socket, &mut buf[len..want]).await?;
+        }
+        return Ok(len);
+    }
+}
+
+fn content_length(head: &[u8]) -> usize {
+    core::str::from_utf8(head)
+        .unwrap_or_default()
+        .split("\r\n")
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+async fn handle(stack: Stack<'static>, request: &[u8], page: &mut Page) {
+    // The body of a logo upload is binary, so only the head is taken as text
+    let (head, body) = match request.windows(4).position(|w| w == b"\r\n\r\n") {
+        Some(end) => (&request[..end], &request[end + 4..]),
+        None => (request, &[][..]),
+    };
+    let head = core::str::from_utf8(head).unwrap_or_default();
+    let mut request_line = head.split(' ');
+    let method = request_line.next().unwrap_or_default();
+    let target = request_line.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let written = match (method, path) {
+        ("GET", "/") => status_page(page).await,
+        ("POST", "/settings") => {
+            let form = form_fields(core::str::from_utf8(body).unwrap_or_default());
+            let pin = form.clone().find(|(key, _)| *key == "pin").map(|(_, v)| v);
+            if pin_ok(pin).await {
+                apply_settings(stack, form.filter(|(key, _)| *key != "pin")).await;
+                page.write_str("HTTP/1.1 303 See Other\r\nLocation: /\r\nConnection: close\r\n\r\n")
+            } else {
+                forbidden(page, "settings")
+            }
+        }
+        // The PIN goes in the query, the body is the image or nothing to restore the default
+        ("POST", "/logo") => {
+            let pin = form_fields(query)
+                .find(|(key, _)| *key == "pin")
+                .map(|(_, v)| v);
+            if pin_ok(pin).await {
+                let reply = upload_logo(body);
+                write!(
+                    page,
+                    "HTTP/1.1 {reply}\r\nConnection: close\r\n\r\n{reply}\r\n"
+                )
+            } else {
+                forbidden(page, "logo")
+            }
+        }
+        _ => page.write_str("HTTP/1.1 404 Not Found\r\nConnection: close\r\n\r\nNot found\r\n"),
+    };
+    // Better an error than a page cut off part way
+    if written.is_err() {
+        logger::log(
+            Level::Error,
+            "http",
+            format_args!("reply to {} {} too large", method, path),
+        );
+        page.len = 0;
+        let _ = page.write_str(
+            "HTTP/1.1 500 Internal Server Error\r\nConnection: close\r\n\r\nPage too large\r\n",
+        );
+    }
+}
+
+fn form_fields(s: &str) -> impl Iterator<Item = (&str, StaticString<32>)> + Clone {
+    s.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key, url_decode(value)))
+}
+
+fn forbidden(page: &mut Page, what: &str) -> fmt::Result {
+    logger::log(
+        Level::Warn,
+        "http",
+        format_args!("{} refused, bad PIN", what),
+    );
+    page.write_str("HTTP/1.1 403 Forbidden\r\nConnection: close\r\n\r\nWrong PIN\r\n")
+}
+
+/// Hands a 128x64 image, one bit per pixel in display page order, over to be saved.
+/// Returns the status line to reply with.
+fn upload_logo(image: &[u8]) -> &'static str {
+    let logo = match image.len() {
+        0 => None,
+        LOGO_LEN => {
+            let mut logo = [0u8; LOGO_LEN];
+            logo.copy_from_slice(image);
+            Some(logo)
+        }
+        n => {
+            logger::log(
+                Level::Warn,
+                "http",
+                format_args!("logo upload has {} bytes", n),
+            );
+            ui::notify(Priority::Warning, "Bad logo upload");
+            return "400 Bad Request";
+        }
+    };
+    logger::log(Level::Info, "http", format_args!("logo upload"));
+    if settings::store_logo(logo) {
+        "202 Accepted"
+    } else {
+        ui::notify(Priority::Warning, "Logo busy, retry");
+        "503 Service Unavailable"
+    }
+}
+
+/// Whether `pin` matches the one set on the unit. Without one set nothing is accepted, so
+/// anyone on the show network can't change settings on a unit nobody has configured.
+pub async fn pin_ok(pin: Option<StaticString<32>>) -> bool {
+    let expected = STATE.lock().await.web_pin;
+    expected.len() > 0 && pin.is_some_and(|pin| pin.str() == expected.str())
+}
+
+async fn status_page(page: &mut Page) -> fmt::Result {
+    let state = STATE.lock().await.clone();
+    let snap = diagnostics::snapshot();
+    let name = Escaped(state.device_name());
+
+    page.write_str("HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nConnection: close\r\n\r\n")?;
+    write!(
+        page,
+        "<!DOCTYPE html><html><head><title>{name}</title>\
+         <meta name=viewport content=\"width=device-width\"></head><body><h1>{name}</h1><table>"
+    )?;
+    write!(
+        page,
+        "<tr><td>Link</td><td>{}</td></tr><tr><td>Address</td><td>{}</td></tr>\
+         <tr><td>MAC</td><td>{}</td></tr>",
+        state.link.label(),
+        Octets(state.self_ip.addr),
+        state.mac,
+    )?;
+    let now = Instant::now();
+    for (slot, core) in state
+        .cores
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.configured())
+    {
+        write!(
+            page,
+            "<tr><td>Core {}</td><td>{}:{}",
+            Core::role(slot),
+            Octets(core.ip.addr),
+            core.ip.port,
+        )?;
+        if let Some(heard) = core.last_heartbeat {
+            write!(page, ", heard {} s ago", (now - heard).as_secs())?;
+        }
+        write!(page, ", last request {}", core.last_send.label())?;
+        if slot == state.active_core {
+            page.write_str(", active")?;
+        }
+        page.write_str("</td></tr>")?;
+    }
+    write!(
+        page,
+        "<tr><td>Cue</td><td>{} {}</td></tr><tr><td>Mark</td><td>{}</td></tr>\
+         <tr><td>Bar</td><td>{}.{}</td></tr><tr><td>Tempo</td><td>{}</td></tr>",
+        state.cue_idx,
+        Escaped(state.cue_metadata.human_ident.str()),
+        Escaped(state.mark_label.str()),
+        state.beat.bar_number,
+        state.beat.count,
+        state.beat.tempo(),
+    )?;
+    write!(
+        page,
+        "<tr><td>Packets</td><td>rx {} tx {}</td></tr><tr><td>Uptime</td><td>{} s</td></tr>\
+         </table>",
+        snap.rx,
+        snap.tx,
+        now.as_secs(),
+    )?;
+
+    let dhcp = state.net_mode == NetMode::Dhcp;
+    write!(
+        page,
+        "<h2>Settings</h2><form method=post action=/settings>\
+         <p>Device name <input name=name maxlength=31 value=\"{name}\"></p>\
+         <p>Core <input name=core_ip value=\"{}\"> port <input name=core_port value={}></p>\
+         <p>Backup core <input name=backup_ip value=\"{}\"> empty for none</p>\
+         <p>Master <select name=master>{}</select> mirror control \
+         <select name=mirror><option value=off>Off</option><option value=on{}>On</option>\
+         </select></p>\
+         <p>Network <select name=net_mode><option value=dhcp{}>DHCP</option>\
+         <option value=static{}>Static</option></select> address \
+         <input name=static_ip value=\"{}\"> netmask <input name=netmask value=\"{}\"> \
+         gateway <input name=gateway value=\"{}\"></p>",
+        Octets(state.cores[0].ip.addr),
+        state.cores[0].ip.port,
+        OptionalAddress(state.cores[1].ip.addr),
+        MasterOptions(&state),
+        if network2::mirror_control() {
+            " selected"
+        } else {
+            ""
+        },
+        if dhcp { " selected" } else { "" },
+        if dhcp { "" } else { " selected" },
+        Octets(state.static_ip.addr),
+        Octets(netmask(state.static_prefix)),
+        OptionalAddress(state.gateway.addr),
+    )?;
+    page.write_str("<p>Log host <input name=log_host value=\"")?;
+    if state.log_host.addr != [0, 0, 0, 0] {
+        write!(page, "{}", Octets(state.log_host.addr))?;
+    }
+    page.write_str("\"> empty keeps them on the unit</p><p>OSC to <input name=osc_host value=\"")?;
+    if state.osc_host.addr != [0, 0, 0, 0] {
+        write!(page, "{}", Octets(state.osc_host.addr))?;
+    }
+    write!(
+        page,
+        "\"> port <input name=osc_port value={}> empty sends no OSC</p>\
+         <p>Metronome tempo <input name=tempo> bpm</p>\
+         <p>LED brightness <input name=led value={}> %</p>{}\
+         <p><input type=submit value=Apply></p></form></body></html>",
+        state.osc_host.port,
+        led::level(),
+        if state.web_pin.len() > 0 {
+            "<p>PIN <input name=pin type=password inputmode=numeric></p>"
+        } else {
+            "<p>Set a web PIN in the menu on the unit to change settings here.</p>"
+        },
+    )
+}
+
+fn netmask(prefix: u8) -> [u8; 4] {
+    u32::MAX
+        .checked_shl(32 - prefix.min(32) as u32)
+        .unwrap_or(0)
+        .to_be_bytes()
+}
+
+/// The prefix length of a dotted netmask, None unless its ones are contiguous.
+fn prefix_len(mask: [u8; 4]) -> Option<u8> {
+    let bits = u32::from_be_bytes(mask);
+    let prefix = bits.leading_ones();
+    (bits.count_ones() == prefix).then_some(prefix as u8)
+}
+
+/// Decodes an `application/x-www-form-urlencoded` value.
+fn url_decode(s: &str) -> StaticString<32> {
+    let mut buf = [0u8; 32];
+    let mut len = 0;
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() && len < buf.len() {
+        buf[len] = match bytes[i] {
+            b'+' => b' ',
+            b'%' if i + 2 < bytes.len() => {
+                let hex = core::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+                i += 2;
+                u8::from_str_radix(hex, 16).unwrap_or(b'?')
+            }
+            b => b,
+        };
+        len += 1;
+        i += 1;
+    }
+    // Cutting it to fit can split a character, keep what comes before it
+    let text = match core::str::from_utf8(&buf[..len]) {
+        Ok(text) => text,
+        Err(err) => core::str::from_utf8(&buf[..err.valid_up_to()]).unwrap_or_default(),
+    };
+    StaticString::new(text)
+}
+
+/// Names `apply_settings` takes, besides `osc_<button>`.
+const SETTINGS: [&str; 17] = [
+    "name",
+    "core_ip",
+    "backup_ip",
+    "backup2_ip",
+    "backup3_ip",
+    "core_port",
+    "net_mode",
+    "static_ip",
+    "netmask",
+    "gateway",
+    "log_host",
+    "osc_host",
+    "master",
+    "mirror",
+    "osc_port",
+    "tempo",
+    "led",
+];
+
+pub fn is_setting(key: &str) -> bool {
+    SETTINGS.contains(&key)
+        || key
+            .strip_prefix("osc_")
+            .and_then(ButtonId::from_name)
+            .is_some()
+}
+
+/// Applies settings by the names the form uses, reconnecting if the network side changed.
+/// Returns false if any name was not recognised.
+pub async fn apply_settings<'a>(
+    stack: Stack<'static>,
+    settings: impl Iterator<Item = (&'a str, StaticString<32>)>,
+) -> bool {
+    let mut state = STATE.lock().await;
+    let before = state.clone();
+    let mut tempo = None;
+    let mut known = true;
+    let mut master = None;
+
+    for (key, value) in settings {
+        let v = value.str().trim();
+        match key {
+            "name" => state.device_name = StaticString::new(v),
+            "core_ip" => {
+                let primary = &mut state.cores[0];
+                primary.ip = IpAddress::from_str_and_port(v, primary.ip.port).unwrap_or(primary.ip)
+            }
+            "backup_ip" | "backup2_ip" | "backup3_ip" => {
+                let slot = match key {
+                    "backup_ip" => 1,
+                    "backup2_ip" => 2,
+                    _ => 3,
+                };
+                let backup = &mut state.cores[slot];
+                backup.ip = if v.is_empty() {
+                    IpAddress {
+                        port: backup.ip.port,
+                        addr: [0, 0, 0, 0],
+                    }
+                } else {
+                    IpAddress::from_str_and_port(v, backup.ip.port).unwrap_or(backup.ip)
+                }
+            }
+            "core_port" => {
+                // All cores of a show listen on the same port
+                if let Ok(port) = v.parse() {
+                    for core in state.cores.iter_mut() {
+                        core.ip.port = port;
+                    }
+                }
+            }
+            "net_mode" => {
+                state.net_mode = if v == "static" {
+                    NetMode::Static
+                } else {
+                    NetMode::Dhcp
+                }
+            }
+            "static_ip" => {
+                state.static_ip = IpAddress::from_str_and_port(v, 0).unwrap_or(state.static_ip)
+            }
+            "netmask" => {
+                if let Some(prefix) = v
+                    .parse::<Ipv4Addr>()
+                    .ok()
+                    .and_then(|mask| prefix_len(mask.octets()))
+                {
+                    state.static_prefix = prefix;
+                }
+            }
+            "gateway" => {
+                state.gateway = if v.is_empty() {
+                    IpAddress {
+                        port: 0,
+                        addr: [0, 0, 0, 0],
+                    }
+                } else {
+                    IpAddress::from_str_and_port(v, 0).unwrap_or(state.gateway)
+                }
+            }
+            "log_host" => {
+                state.log_host = if v.is_empty() {
+                    IpAddress {
+                        port: logger::SYSLOG_PORT,
+                        addr: [0, 0, 0, 0],
+                    }
+                } else {
+                    IpAddress::from_str_and_port(v, logger::SYSLOG_PORT).unwrap_or(state.log_host)
+                }
+            }
+            "osc_host" => {
+                state.osc_host = if v.is_empty() {
+                    IpAddress {
+                        port: state.osc_host.port,
+                        addr: [0, 0, 0, 0],
+                    }
+                } else {
+                    IpAddress::from_str_and_port(v, state.osc_host.port).unwrap_or(state.osc_host)
+                }
+            }
+            "master" => match (0..MAX_CORES).find(|slot| Core::role(*slot).eq_ignore_ascii_case(v))
+            {
+                Some(slot) => master = Some(slot),
+                None => known = false,
+            },
+            "mirror" => network2::set_mirror_control(v == "on"),
+            "osc_port" => state.osc_host.port = v.parse().unwrap_or(state.osc_host.port),
+            "tempo" => tempo = v.parse::<i64>().ok().filter(|t| *t > 0),
+            "led" => {
+                if let Ok(level) = v.parse() {
+                    led::set_level(level);
+                }
+            }
+            _ => match key.strip_prefix("osc_").and_then(ButtonId::from_name) {
+                Some(id) => state.osc_addresses[id as usize] = StaticString::new(v),
+                None => known = false,
+            },
+        }
+    }
+
+    let renamed = state.device_name.str() != before.device_name.str();
+    // A new name goes out as the DHCP hostname the next time we ask for an address
+    let net_changed = state.net_mode != before.net_mode
+        || (state.net_mode == NetMode::Static
+            && (state.static_ip.addr != before.static_ip.addr
+                || state.static_prefix != before.static_prefix
+                || state.gateway.addr != before.gateway.addr))
+        || (state.net_mode == NetMode::Dhcp && renamed);
+    let reconnect = net_changed
+        || state
+            .cores
+            .iter()
+            .zip(before.cores.iter())
+            .any(|(now, was)| now.ip.addr != was.ip.addr || now.ip.port != was.ip.port)
+        || state.log_host.addr != before.log_host.addr
+        || state.osc_host.addr != before.osc_host.addr
+        || state.osc_host.port != before.osc_host.port
+        || renamed
+        || master.is_some_and(|slot| slot != network2::master_core());
+    if let Some(slot) = master {
+        network2::set_master_core(slot);
+    }
+    if net_changed {
+        network2::apply_net_mode(stack, &state);
+    }
+    drop(state);
+    settings::save();
+
+    logger::log(Level::Info, "settings", format_args!("applied remotely"));
+    ui::notify(Priority::Info, "Settings applied");
+    if let Some(t) = tempo {
+        ACTION_UPSTREAM.send(Action::MetronomeSetTempo(t)).await;
+    }
+    if reconnect {
+        ACTION_UPSTREAM.send(Action::ReloadConnection).await;
+    }
+    known
+}
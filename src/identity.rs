@@ -0,0 +1,83 @@
+use crate::logger::{self, Level};
+use crate::settings::SettingsFlash;
+use core::fmt;
+
+/// Longest hostname the DHCP client will send.
+const HOSTNAME_LEN: usize = 32;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MacAddress(pub [u8; 6]);
+
+impl MacAddress {
+    /// Locally administered and unicast, with the rest hashed from the flash chip's unique
+    /// ID. Every unit gets its own address and keeps it across reboots and updates.
+    pub fn from_unique_id(id: u64) -> Self {
+        // FNV-1a, so all of the ID counts rather than just the five bytes that fit
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in id.to_be_bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        let [_, _, _, a, b, c, d, e] = hash.to_be_bytes();
+        Self([0x02, a, b, c, d, e])
+    }
+
+    /// Locally administered and unicast, the rest from `seed`. For when there is no unique
+    /// ID to go by, so units without one don't all end up on the same address.
+    pub fn random(seed: u64) -> Self {
+        let [_, _, _, a, b, c, d, e] = seed.to_be_bytes();
+        Self([0x02, a, b, c, d, e])
+    }
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(
+            f,
+            "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+            a, b, c, d, e, g
+        )
+    }
+}
+
+/// The 64-bit unique ID of the flash chip, which the RP2040 itself doesn't have.
+pub fn read_unique_id(flash: &mut SettingsFlash) -> Option<u64> {
+    let mut id = [0u8; 8];
+    match flash.blocking_unique_id(&mut id) {
+        Ok(()) => Some(u64::from_be_bytes(id)),
+        Err(err) => {
+            logger::log(
+                Level::Error,
+                "identity",
+                format_args!("no flash unique id: {:?}", err),
+            );
+            None
+        }
+    }
+}
+
+/// The device name as a hostname: lower case letters and digits, anything else between
+/// them turned into a single hyphen.
+pub fn hostname(name: &str) -> heapless::String<HOSTNAME_LEN> {
+    let mut host = heapless::String::new();
+    let mut gap = false;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            gap = !host.is_empty();
+            continue;
+        }
+        if gap && host.push('-').is_err() {
+            break;
+        }
+        gap = false;
+        if host.push(c.to_ascii_lowercase()).is_err() {
+            break;
+        }
+    }
+    // Cut off right after a hyphen, which a hostname can't end with
+    if host.ends_with('-') {
+        host.pop();
+    }
+    host
+}
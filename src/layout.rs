@@ -0,0 +1,107 @@
+use embedded_graphics::prelude::Point;
+use portable_atomic::{AtomicU8, Ordering};
+
+/// Building blocks of the main screen. Each is drawn at the origin its layout gives it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Widget {
+    Bpm,
+    /// Cue number and name in small text
+    Cue,
+    /// Cue number and name in large text
+    CueLarge,
+    Mark,
+    Bar,
+    /// Bar number at double the large font
+    BarGiant,
+    BeatGrid,
+    /// Transport state and playhead in small text
+    Status,
+    /// Playhead alone in large text
+    Playhead,
+    Link,
+    /// Featured show timer in large text, with its name underneath
+    Timer,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    Standard,
+    BigBar,
+    CueFocus,
+    CueTimer,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 4] = [
+        Layout::Standard,
+        Layout::BigBar,
+        Layout::CueFocus,
+        Layout::CueTimer,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Layout::Standard => "Standard",
+            Layout::BigBar => "Big bar",
+            Layout::CueFocus => "Cue focus",
+            Layout::CueTimer => "Cue + timer",
+        }
+    }
+
+    pub fn next(&self) -> Layout {
+        Layout::ALL[(*self as usize + 1) % Layout::ALL.len()]
+    }
+
+    // The right and bottom pixel are kept clear, the burn-in shift moves everything onto them
+    fn widgets(&self) -> &'static [(Widget, Point)] {
+        match self {
+            Layout::Standard => &[
+                (Widget::Bpm, Point::new(0, 0)),
+                (Widget::Cue, Point::new(24, 0)),
+                (Widget::Link, Point::new(121, 0)),
+                (Widget::Mark, Point::new(0, 11)),
+                (Widget::Bar, Point::new(0, 33)),
+                (Widget::BeatGrid, Point::new(40, 33)),
+                (Widget::Status, Point::new(70, 33)),
+            ],
+            Layout::BigBar => &[
+                (Widget::Bpm, Point::new(0, 0)),
+                (Widget::Link, Point::new(121, 0)),
+                (Widget::BarGiant, Point::new(0, 11)),
+                (Widget::BeatGrid, Point::new(80, 24)),
+            ],
+            Layout::CueFocus => &[
+                (Widget::CueLarge, Point::new(0, 0)),
+                (Widget::Link, Point::new(121, 0)),
+                (Widget::Playhead, Point::new(0, 24)),
+                (Widget::BeatGrid, Point::new(60, 26)),
+                (Widget::Bar, Point::new(90, 24)),
+            ],
+            Layout::CueTimer => &[
+                (Widget::CueLarge, Point::new(0, 0)),
+                (Widget::Link, Point::new(121, 0)),
+                (Widget::Timer, Point::new(0, 22)),
+                (Widget::BeatGrid, Point::new(72, 24)),
+                (Widget::Bar, Point::new(97, 22)),
+            ],
+        }
+    }
+
+    /// Where `widget` goes in this layout, if it is shown at all.
+    pub fn place(&self, widget: Widget) -> Option<Point> {
+        self.widgets()
+            .iter()
+            .find(|(w, _)| *w == widget)
+            .map(|(_, origin)| *origin)
+    }
+}
+
+static LAYOUT: AtomicU8 = AtomicU8::new(Layout::Standard as u8);
+
+pub fn layout() -> Layout {
+    Layout::ALL[LAYOUT.load(Ordering::Relaxed) as usize]
+}
+
+pub fn set_layout(layout: Layout) {
+    LAYOUT.store(layout as u8, Ordering::Relaxed);
+}
@@ -1,7 +1,29 @@
 use crate::{events::Action, ui::debug, LED_CH};
 use bitflags::bitflags;
+use embassy_futures::select::{select, Either};
 use embassy_rp::pwm::{self, SetDutyCycle};
-use embassy_time::Timer;
+use embassy_time::{Duration, Ticker, Timer};
+use portable_atomic::{AtomicU8, Ordering};
+
+static LEVEL: AtomicU8 = AtomicU8::new(100);
+
+/// Brightness of a lit LED, in percent.
+pub fn level() -> u8 {
+    LEVEL.load(Ordering::Relaxed)
+}
+
+pub fn set_level(percent: u8) {
+    LEVEL.store(percent.min(100), Ordering::Relaxed);
+}
+
+/// Steps of the timer alarm, every LED at once: two quick flashes a second, unlike any
+/// beat or state the LEDs otherwise show.
+const ALARM_PATTERN: [bool; 8] = [true, false, true, false, false, false, false, false];
+const ALARM_STEP: Duration = Duration::from_millis(125);
+
+fn on_value() -> u16 {
+    (u16::MAX as u32 * level() as u32 / 100) as u16
+}
 
 bitflags! {
     #[derive(PartialEq, Clone, Copy)]
@@ -22,6 +44,8 @@ pub struct LEDController {
     conn_val: u16,
     play_val: u16,
     vlt_val: u16,
+    // While set the values above are kept up to date, but the LEDs show the alarm
+    alarm: bool,
 }
 
 impl LEDController {
@@ -37,6 +61,7 @@ impl LEDController {
                     conn_val: 0,
                     play_val: 0,
                     vlt_val: 0,
+                    alarm: false,
                 };
             }
         }
@@ -64,14 +89,14 @@ impl LEDController {
     }
 
     pub fn set(&mut self, led: LED, val: u16) {
-        self.demap_pin(led).set_duty_cycle(val);
+        if !self.alarm {
+            self.demap_pin(led).set_duty_cycle(val);
+        }
         *self.demap_val(led) = val;
     }
 
     pub fn set_bool(&mut self, led: LED, on: bool) {
-        let val = if on { u16::MAX } else { 0 };
-        self.demap_pin(led).set_duty_cycle(val);
-        *self.demap_val(led) = val;
+        self.set(led, if on { on_value() } else { 0 });
     }
 
     pub fn toggle(&mut self, led: LED) {
@@ -82,16 +107,57 @@ impl LEDController {
     }
 
     pub async fn flash(&mut self, led: LED, duration_us: u64) {
-        self.set(led, u16::MAX);
+        if self.alarm {
+            return;
+        }
+        self.set(led, on_value());
         Timer::after_micros(duration_us).await;
         self.set(led, u16::MIN);
     }
+
+    /// Starts or stops the alarm. Stopping puts back what each LED should be showing.
+    pub fn set_alarm(&mut self, alarm: bool) {
+        self.alarm = alarm;
+        for led in [LED::Metronome, LED::Connection, LED::Playing, LED::VLT] {
+            let val = if alarm { 0 } else { *self.demap_val(led) };
+            self.demap_pin(led).set_duty_cycle(val);
+        }
+    }
+
+    fn show_alarm(&mut self, on: bool) {
+        let val = if on { on_value() } else { 0 };
+        for led in [LED::Metronome, LED::Connection, LED::Playing, LED::VLT] {
+            self.demap_pin(led).set_duty_cycle(val);
+        }
+    }
 }
 
 #[embassy_executor::task]
 pub async fn led_task(mut c: LEDController) {
+    let mut alarm = Ticker::every(ALARM_STEP);
+    let mut step = 0;
     loop {
-        match LED_CH.receive().await {
+        let action = if c.alarm {
+            match select(LED_CH.receive(), alarm.next()).await {
+                Either::First(action) => action,
+                Either::Second(_) => {
+                    step = (step + 1) % ALARM_PATTERN.len();
+                    c.show_alarm(ALARM_PATTERN[step]);
+                    continue;
+                }
+            }
+        } else {
+            LED_CH.receive().await
+        };
+        match action {
+            Action::TimerAlarm(on) => {
+                c.set_alarm(on);
+                alarm.reset();
+                step = 0;
+                if on {
+                    c.show_alarm(ALARM_PATTERN[0]);
+                }
+            }
             Action::LEDSet(led, state) => {
                 c.set_bool(led, state);
             }
@@ -110,6 +176,8 @@ pub async fn led_task(mut c: LEDController) {
             }
             Action::LoseConnection => {
                 c.set_bool(LED::Connection, false);
+                c.set_bool(LED::Playing, false);
+                c.set_bool(LED::VLT, false);
             }
             Action::NewBeatData(_) => {
                 c.flash(LED::Metronome, 10000).await;
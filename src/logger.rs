@@ -0,0 +1,158 @@
+use common::mem::str::StaticString;
+use core::cell::RefCell;
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use embassy_time::Instant;
+use portable_atomic::{AtomicU8, Ordering};
+
+pub const SYSLOG_PORT: u16 = 514;
+const LOG_CAPACITY: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Level {
+    pub const ALL: [Level; 4] = [Level::Error, Level::Warn, Level::Info, Level::Debug];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Level::Error => "Error",
+            Level::Warn => "Warn",
+            Level::Info => "Info",
+            Level::Debug => "Debug",
+        }
+    }
+
+    /// RFC 5424 severity, sent with facility local0.
+    fn severity(&self) -> u8 {
+        match self {
+            Level::Error => 3,
+            Level::Warn => 4,
+            Level::Info => 6,
+            Level::Debug => 7,
+        }
+    }
+
+    pub fn next(&self) -> Level {
+        Level::ALL[(*self as usize + 1) % Level::ALL.len()]
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct LogRecord {
+    pub level: Level,
+    pub source: &'static str,
+    pub timestamp_ms: u64,
+    pub msg: StaticString<64>,
+}
+
+impl LogRecord {
+    /// Formats the record as a syslog datagram.
+    pub fn to_syslog<'a>(&self, buf: &'a mut [u8]) -> &'a str {
+        format_no_std::show(
+            buf,
+            format_args!(
+                "<{}>clicks {} {}.{:03}: {}",
+                16 * 8 + self.level.severity(),
+                self.source,
+                self.timestamp_ms / 1000,
+                self.timestamp_ms % 1000,
+                self.msg.str()
+            ),
+        )
+        .unwrap_or_default()
+    }
+}
+
+/// Oldest records are overwritten once full, so a long disconnect keeps the most recent history.
+struct LogRing {
+    records: [Option<LogRecord>; LOG_CAPACITY],
+    head: usize,
+    len: usize,
+}
+
+impl LogRing {
+    const fn new() -> Self {
+        Self {
+            records: [None; LOG_CAPACITY],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, record: LogRecord) {
+        let idx = (self.head + self.len) % LOG_CAPACITY;
+        self.records[idx] = Some(record);
+        if self.len == LOG_CAPACITY {
+            self.head = (self.head + 1) % LOG_CAPACITY;
+        } else {
+            self.len += 1;
+        }
+    }
+
+    fn pop(&mut self) -> Option<LogRecord> {
+        if self.len == 0 {
+            return None;
+        }
+        let record = self.records[self.head].take();
+        self.head = (self.head + 1) % LOG_CAPACITY;
+        self.len -= 1;
+        record
+    }
+}
+
+static LOG_RING: CriticalSectionMutex<RefCell<LogRing>> =
+    CriticalSectionMutex::new(RefCell::new(LogRing::new()));
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+
+pub fn level() -> Level {
+    Level::ALL[LOG_LEVEL.load(Ordering::Relaxed) as usize]
+}
+
+pub fn set_level(level: Level) {
+    LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn log(level: Level, source: &'static str, args: core::fmt::Arguments) {
+    let mut buf = [0u8; 64];
+    let msg = format_no_std::show(&mut buf, args).unwrap_or("<fmt failed>");
+    match level {
+        Level::Error => defmt::error!("{}: {}", source, msg),
+        Level::Warn => defmt::warn!("{}: {}", source, msg),
+        Level::Info => defmt::info!("{}: {}", source, msg),
+        Level::Debug => defmt::debug!("{}: {}", source, msg),
+    }
+
+    if level <= self::level() {
+        queue(level, source, msg);
+    }
+}
+
+/// Queues a record for the log host without the level check, for reports that were asked
+/// for explicitly.
+pub fn send(level: Level, source: &'static str, args: core::fmt::Arguments) {
+    let mut buf = [0u8; 64];
+    queue(
+        level,
+        source,
+        format_no_std::show(&mut buf, args).unwrap_or("<fmt failed>"),
+    );
+}
+
+fn queue(level: Level, source: &'static str, msg: &str) {
+    let record = LogRecord {
+        level,
+        source,
+        timestamp_ms: Instant::now().as_millis(),
+        msg: StaticString::new(msg),
+    };
+    LOG_RING.lock(|ring| ring.borrow_mut().push(record));
+}
+
+pub fn pop() -> Option<LogRecord> {
+    LOG_RING.lock(|ring| ring.borrow_mut().pop())
+}
@@ -2,22 +2,35 @@
 #![no_main]
 
 mod buttons;
+mod console;
+mod diagnostics;
+mod eventlog;
 mod events;
+mod firmware;
 mod fsm;
 mod graphics;
+mod http;
+mod identity;
+mod layout;
 mod led;
+mod logger;
 mod menu;
 mod metronome;
+mod netutil;
 //mod network;
 mod network2;
+mod osc;
 //mod spicks;
+mod settings;
 mod state;
+mod supervisor;
 mod textentry;
+mod timers;
+mod toast;
 mod translator;
 mod ui;
 
 use defmt_rtt as _;
-use panic_probe as _;
 
 use alloc_cortex_m::CortexMHeap;
 
@@ -26,12 +39,17 @@ static ALLOCATOR: CortexMHeap = CortexMHeap::empty();
 
 use crate::{
     buttons::{button_scanner_task, ButtonScanner},
-    events::{Action, ButtonEvent, Mode},
+    diagnostics::ChannelId,
+    events::{Action, ButtonEvent, Mode, Priority},
     graphics::GraphicsController,
     led::{led_task, LEDController},
+    logger::Level,
     metronome::metronome_task,
+    settings::settings_task,
     state::SystemState,
+    supervisor::supervisor_task,
     textentry::text_entry_task,
+    timers::timer_task,
     translator::input_translator_task,
     ui::ui_task,
 };
@@ -45,6 +63,7 @@ use embassy_rp::{
     peripherals::{I2C1, PIO0, PWM_SLICE0},
     pio, pwm,
     spi::{self, Async, Spi},
+    watchdog::Watchdog,
 };
 use embassy_sync::{
     blocking_mutex::{
@@ -66,20 +85,23 @@ use embassy_net::{Stack, StackResources};
 use embassy_net_wiznet::chip::W5500;
 use embassy_net_wiznet::*;
 
+pub type ActionChannel = Channel<CriticalSectionRawMutex, Action, 8>;
+
 // Button events from scanner → translator
 pub static BUTTON_CH: Channel<CriticalSectionRawMutex, ButtonEvent, 8> = Channel::new();
 
 // Translator publishes actions here
-pub static ACTION_SRC: Channel<CriticalSectionRawMutex, Action, 8> = Channel::new();
+pub static ACTION_SRC: ActionChannel = Channel::new();
 // Everyone else publishes actions here
-pub static ACTION_UPSTREAM: Channel<CriticalSectionRawMutex, Action, 8> = Channel::new();
+pub static ACTION_UPSTREAM: ActionChannel = Channel::new();
 
 // Fan‑out destinations (subscribers)
-pub static CONTROL_CH: Channel<CriticalSectionRawMutex, Action, 8> = Channel::new();
-pub static UI_CH: Channel<CriticalSectionRawMutex, Action, 8> = Channel::new();
-pub static UX_CH: Channel<CriticalSectionRawMutex, Action, 8> = Channel::new();
-pub static LED_CH: Channel<CriticalSectionRawMutex, Action, 8> = Channel::new();
-pub static METR_CH: Channel<CriticalSectionRawMutex, Action, 8> = Channel::new();
+pub static CONTROL_CH: ActionChannel = Channel::new();
+pub static UI_CH: ActionChannel = Channel::new();
+pub static UX_CH: ActionChannel = Channel::new();
+pub static LED_CH: ActionChannel = Channel::new();
+pub static METR_CH: ActionChannel = Channel::new();
+pub static TIMER_CH: ActionChannel = Channel::new();
 
 // Signal for latest mode
 pub static MODE_SIGNAL: Signal<CriticalSectionRawMutex, Mode> = Signal::new();
@@ -106,8 +128,39 @@ async fn main(spawner: Spawner) -> () {
     let mut config = Config::default();
     let mut p = embassy_rp::init(config);
 
-    //let mut spi_config = spi::Config::default();
+    let watchdog = Watchdog::new(p.WATCHDOG);
+    let reset_report = supervisor::take_reset_report(watchdog.reset_reason());
+    if reset_report.is_crash() {
+        logger::log(
+            Level::Warn,
+            "main",
+            format_args!("recovered from crash: {}", reset_report.message.str()),
+        );
+    }
+    STATE.lock().await.reset_report = Some(reset_report);
+
     let mut rng = RoscRng;
+    let mut flash = settings::SettingsFlash::new_blocking(p.FLASH);
+    let unique_id = identity::read_unique_id(&mut flash);
+    // A new address every boot, which leases and switch tables won't like, but it won't
+    // clash with another unit that couldn't read its ID either
+    let mac_addr = match unique_id {
+        Some(id) => identity::MacAddress::from_unique_id(id),
+        None => identity::MacAddress::random(rng.next_u64()),
+    };
+    {
+        let mut state = STATE.lock().await;
+        state.unique_id = unique_id;
+        state.mac = mac_addr;
+        settings::load(&mut flash, &mut state);
+    }
+    settings::load_logo(&mut flash);
+    static FLASH: StaticCell<settings::SharedFlash> = StaticCell::new();
+    let flash: &'static settings::SharedFlash = FLASH.init(
+        embassy_sync::blocking_mutex::Mutex::new(core::cell::RefCell::new(flash)),
+    );
+
+    //let mut spi_config = spi::Config::default();
     let mut led = Output::new(p.PIN_19, Level::Low);
 
     // The W55RP20 uses a PIO unit for SPI communication, once the SPI bus has been formed using a
@@ -140,7 +193,6 @@ async fn main(spawner: Spawner) -> () {
     let w5500_int = Input::new(p.PIN_24, Pull::Up);
     let w5500_reset = Output::new(p.PIN_25, Level::High);
 
-    let mac_addr = [0x02, 0x00, 0x00, 0x00, 0x00, 0x00];
     static NET_STATE: StaticCell<State<8, 8>> = StaticCell::new();
     let state = NET_STATE.init(State::<8, 8>::new());
 
@@ -156,7 +208,7 @@ async fn main(spawner: Spawner) -> () {
         network2::SpiBusType,
         Input<'static>,
         Output<'static>,
-    >(mac_addr, state, spidev, w5500_int, w5500_reset)
+    >(mac_addr.0, state, spidev, w5500_int, w5500_reset)
     .await
     .unwrap();
 
@@ -165,20 +217,21 @@ async fn main(spawner: Spawner) -> () {
 
     let _ = spawner.spawn(network2::ethernet_task(w55_runner));
 
-    // Init network stack
-    static RESOURCES: StaticCell<StackResources<3>> = StaticCell::new();
-    let (stack, netstack_runner) = embassy_net::new(
-        device,
-        embassy_net::Config::dhcpv4(Default::default()),
-        RESOURCES.init(StackResources::new()),
-        seed,
-    );
+    // Init network stack: DHCP or the saved static address, the ClicKS and OSC UDP sockets,
+    // HTTP, the console and updates
+    let config = network2::net_config(&STATE.lock().await);
+    static RESOURCES: StaticCell<StackResources<7>> = StaticCell::new();
+    let (stack, netstack_runner) =
+        embassy_net::new(device, config, RESOURCES.init(StackResources::new()), seed);
 
     // Launch network task
     let _ = spawner.spawn(network2::net_task(netstack_runner));
 
     // Launch network handler task
     let _ = spawner.spawn(network2::stack_task(stack));
+    let _ = spawner.spawn(http::http_task(stack));
+    let _ = spawner.spawn(console::console_task(stack));
+    let _ = spawner.spawn(firmware::firmware_task(stack, flash));
 
     //spi_config.frequency = 20_000_000;
 
@@ -228,7 +281,7 @@ async fn main(spawner: Spawner) -> () {
     let pwm_config = pwm::Config::default();
 
     //   let _ = spawner.spawn(network2::ethernet_task(spi));
-    let _ = spawner.spawn(ui_task(GraphicsController::new(i2c)));
+    let _ = spawner.spawn(ui_task(GraphicsController::new(i2c).await));
     let _ = spawner.spawn(input_translator_task());
     let _ = spawner.spawn(text_entry_task());
     let _ = spawner.spawn(action_fanout_task());
@@ -243,6 +296,18 @@ async fn main(spawner: Spawner) -> () {
         p.PIN_2, p.PIN_3, p.PIN_4, p.PIN_5, p.PIN_6, p.PIN_7, p.PIN_8,
     )));
     let _ = spawner.spawn(metronome_task());
+    let _ = spawner.spawn(timer_task());
+    let _ = spawner.spawn(supervisor_task(watchdog));
+    let _ = spawner.spawn(settings_task(flash));
+
+    if reset_report.is_crash() {
+        ui::notify(Priority::Critical, reset_report.short().str());
+    }
+    if unique_id.is_none() {
+        ui::notify(Priority::Warning, "No unique ID, MAC random");
+    }
+
+    firmware::confirm_when_connected(flash).await;
 
     loop {
         Timer::after_secs(600).await;
@@ -406,20 +471,16 @@ async fn main(spawner: Spawner) -> () {
 #[embassy_executor::task]
 pub async fn action_fanout_task() {
     let mut rx = ACTION_SRC.receiver();
-    let tx_playback = CONTROL_CH.sender();
-    let tx_ui = UI_CH.sender();
-    let tx_ux = UX_CH.sender();
-    let tx_led = LED_CH.sender();
-    let tx_metr = METR_CH.sender();
 
     loop {
         let action = rx.receive().await;
 
         // Non‑blocking sends so fanout isn't held up by slow consumer
-        let _ = tx_playback.try_send(action);
-        let _ = tx_ui.try_send(action);
-        let _ = tx_ux.try_send(action);
-        let _ = tx_led.try_send(action);
-        let _ = tx_metr.try_send(action);
+        diagnostics::try_forward(&CONTROL_CH, ChannelId::Control, action);
+        diagnostics::try_forward(&UI_CH, ChannelId::Ui, action);
+        diagnostics::try_forward(&UX_CH, ChannelId::Ux, action);
+        diagnostics::try_forward(&LED_CH, ChannelId::Led, action);
+        diagnostics::try_forward(&METR_CH, ChannelId::Metronome, action);
+        diagnostics::try_forward(&TIMER_CH, ChannelId::Timer, action);
     }
 }
@@ -1,7 +1,12 @@
 use crate::{
     events::{Action, Mode},
-    state::SystemState,
+    graphics, layout, logger,
+    network2::{self, Subscription},
+    settings,
+    state::{Core, SystemState, MAX_CORES},
     textentry::TextEntryContext,
+    timers::{self, TimerId},
+    translator,
 };
 use common::mem::str::StaticString;
 
@@ -12,9 +17,18 @@ pub struct MenuItem {
     pub exec: fn(SystemState) -> Option<Action>,
 }
 
-const MENU_SIZE: usize = 8;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuPage {
+    Root,
+    Display,
+    Subscriptions,
+}
+
+const ROOT_SIZE: usize = 23;
+const DISPLAY_SIZE: usize = 7;
+const SUBSCRIPTIONS_SIZE: usize = 5;
 
-pub fn items() -> [MenuItem; MENU_SIZE] {
+fn root_items() -> [MenuItem; ROOT_SIZE] {
     [
         MenuItem {
             text: StaticString::new("Close Menu"),
@@ -30,8 +44,9 @@ pub fn items() -> [MenuItem; MENU_SIZE] {
             text: StaticString::new("Core port"),
             value: |state| {
                 let mut buf = [0u8; 8];
-                let s = format_no_std::show(&mut buf, format_args!("{:>5}", state.core_ip.port))
-                    .unwrap_or_default();
+                let s =
+                    format_no_std::show(&mut buf, format_args!("{:>5}", state.cores[0].ip.port))
+                        .unwrap_or_default();
                 StaticString::new(s)
             },
             exec: |_| {
@@ -43,7 +58,7 @@ pub fn items() -> [MenuItem; MENU_SIZE] {
         },
         MenuItem {
             text: StaticString::new("IP"),
-            value: |state| state.core_ip.str_from_octets(),
+            value: |state| state.cores[0].ip.str_from_octets(),
             exec: |_| {
                 Some(Action::TextEntryStart {
                     ctx: TextEntryContext::CoreIPv4,
@@ -51,20 +66,186 @@ pub fn items() -> [MenuItem; MENU_SIZE] {
                 })
             },
         },
+        MenuItem {
+            text: StaticString::new("Backup IP"),
+            value: |state| {
+                if state.cores[1].configured() {
+                    state.cores[1].ip.str_from_octets()
+                } else {
+                    StaticString::new("None")
+                }
+            },
+            exec: |_| {
+                Some(Action::TextEntryStart {
+                    ctx: TextEntryContext::BackupIPv4,
+                    initial_value: StaticString::new("192.168.1."),
+                })
+            },
+        },
+        MenuItem {
+            text: StaticString::new("Master core"),
+            value: |_| StaticString::new(Core::role(network2::master_core())),
+            exec: |state| {
+                let current = network2::master_core();
+                let next = (1..=MAX_CORES)
+                    .map(|i| (current + i) % MAX_CORES)
+                    .find(|slot| state.cores[*slot].configured())?;
+                network2::set_master_core(next);
+                settings::save();
+                Some(Action::ReloadConnection)
+            },
+        },
+        MenuItem {
+            text: StaticString::new("Mirror control"),
+            value: |_| {
+                StaticString::new(if network2::mirror_control() {
+                    "On"
+                } else {
+                    "Off"
+                })
+            },
+            exec: |_| {
+                network2::set_mirror_control(!network2::mirror_control());
+                settings::save();
+                Some(Action::ForceRedraw)
+            },
+        },
+        MenuItem {
+            text: StaticString::new("Subscriptions"),
+            value: |_| {
+                let on = Subscription::ALL
+                    .iter()
+                    .filter(|kind| network2::subscribed(**kind))
+                    .count();
+                let mut buf = [0u8; 8];
+                let s = format_no_std::show(
+                    &mut buf,
+                    format_args!("{}/{}", on, Subscription::ALL.len()),
+                )
+                .unwrap_or_default();
+                StaticString::new(s)
+            },
+            exec: |_| Some(Action::OpenMenu(MenuPage::Subscriptions)),
+        },
         MenuItem {
             text: StaticString::new("Me"),
             value: |state| state.self_ip.str_from_octets(),
             exec: |_| None,
         },
         MenuItem {
-            text: StaticString::new("Menu"),
+            text: StaticString::new("Timers"),
             value: |_| StaticString::empty(),
-            exec: |_| None,
+            exec: |_| Some(Action::ModeChange(Mode::Timers)),
         },
         MenuItem {
-            text: StaticString::new("Menu"),
+            text: StaticString::new("Count A length"),
+            value: |state| countdown_length(&state, TimerId::CountdownA),
+            exec: |_| {
+                Some(Action::TextEntryStart {
+                    ctx: TextEntryContext::CountdownA,
+                    initial_value: StaticString::new(""),
+                })
+            },
+        },
+        MenuItem {
+            text: StaticString::new("Count B length"),
+            value: |state| countdown_length(&state, TimerId::CountdownB),
+            exec: |_| {
+                Some(Action::TextEntryStart {
+                    ctx: TextEntryContext::CountdownB,
+                    initial_value: StaticString::new(""),
+                })
+            },
+        },
+        MenuItem {
+            text: StaticString::new("Display"),
             value: |_| StaticString::empty(),
-            exec: |_| None,
+            exec: |_| Some(Action::OpenMenu(MenuPage::Display)),
+        },
+        MenuItem {
+            text: StaticString::new("Log level"),
+            value: |_| StaticString::new(logger::level().label()),
+            exec: |_| {
+                logger::set_level(logger::level().next());
+                settings::save();
+                Some(Action::ForceRedraw)
+            },
+        },
+        MenuItem {
+            text: StaticString::new("Log host"),
+            value: |state| {
+                if state.log_host.addr == [0, 0, 0, 0] {
+                    StaticString::new("None")
+                } else {
+                    state.log_host.str_from_octets()
+                }
+            },
+            exec: |_| {
+                Some(Action::TextEntryStart {
+                    ctx: TextEntryContext::LogHostIPv4,
+                    initial_value: StaticString::new("192.168.1."),
+                })
+            },
+        },
+        MenuItem {
+            text: StaticString::new("Network"),
+            value: |state| StaticString::new(state.link.label()),
+            exec: |_| Some(Action::ModeChange(Mode::Network)),
+        },
+        MenuItem {
+            text: StaticString::new("Web PIN"),
+            value: |state| {
+                StaticString::new(if state.web_pin.len() > 0 {
+                    "Set"
+                } else {
+                    "None"
+                })
+            },
+            exec: |_| {
+                Some(Action::TextEntryStart {
+                    ctx: TextEntryContext::WebPin,
+                    initial_value: StaticString::new(""),
+                })
+            },
+        },
+        MenuItem {
+            text: StaticString::new("Events"),
+            value: |state| {
+                let mut buf = [0u8; 8];
+                let s = format_no_std::show(&mut buf, format_args!("{}", state.events.len()))
+                    .unwrap_or_default();
+                StaticString::new(s)
+            },
+            exec: |_| Some(Action::ModeChange(Mode::Events)),
+        },
+        MenuItem {
+            text: StaticString::new("Diagnostics"),
+            value: |_| StaticString::empty(),
+            exec: |_| Some(Action::ModeChange(Mode::Diagnostics)),
+        },
+        MenuItem {
+            text: StaticString::new("Last reset"),
+            value: |state| {
+                state
+                    .reset_report
+                    .map(|r| r.short())
+                    .unwrap_or(StaticString::empty())
+            },
+            exec: |state| {
+                state.reset_report.map(|r| Action::DebugMessage {
+                    msg: StaticString::new(r.message.str()),
+                })
+            },
+        },
+        MenuItem {
+            text: StaticString::new("Send diagnostics"),
+            value: |_| StaticString::empty(),
+            exec: |_| Some(Action::SendDiagnostics),
+        },
+        MenuItem {
+            text: StaticString::new("About"),
+            value: |_| StaticString::empty(),
+            exec: |_| Some(Action::ModeChange(Mode::About)),
         },
         MenuItem {
             text: StaticString::new("Menu"),
@@ -74,16 +255,155 @@ pub fn items() -> [MenuItem; MENU_SIZE] {
     ]
 }
 
-pub fn get_items_following_idx<const N: usize>(idx: usize) -> [Option<MenuItem>; N] {
-    let n = N.min(MENU_SIZE - idx);
+fn countdown_length(state: &SystemState, id: TimerId) -> StaticString<32> {
+    match state.timers.get(id).length() {
+        Some(length) => StaticString::new(timers::format(length).str()),
+        None => StaticString::empty(),
+    }
+}
+
+fn display_items() -> [MenuItem; DISPLAY_SIZE] {
+    [
+        MenuItem {
+            text: StaticString::new("Back"),
+            value: |_| StaticString::empty(),
+            exec: |_| Some(Action::OpenMenu(MenuPage::Root)),
+        },
+        MenuItem {
+            text: StaticString::new("Layout"),
+            value: |_| StaticString::new(layout::layout().label()),
+            exec: |_| {
+                layout::set_layout(layout::layout().next());
+                settings::save();
+                Some(Action::ForceRedraw)
+            },
+        },
+        MenuItem {
+            text: StaticString::new("Contrast"),
+            value: |_| StaticString::new(graphics::contrast().label()),
+            exec: |_| {
+                graphics::set_contrast(graphics::contrast().next());
+                settings::save();
+                Some(Action::ForceRedraw)
+            },
+        },
+        MenuItem {
+            text: StaticString::new("Idle dim"),
+            value: |_| StaticString::new(graphics::idle_timeout().label()),
+            exec: |_| {
+                graphics::set_idle_timeout(graphics::idle_timeout().next());
+                settings::save();
+                Some(Action::ForceRedraw)
+            },
+        },
+        MenuItem {
+            text: StaticString::new("Scroll speed"),
+            value: |_| StaticString::new(graphics::scroll_speed().label()),
+            exec: |_| {
+                graphics::set_scroll_speed(graphics::scroll_speed().next());
+                settings::save();
+                Some(Action::ForceRedraw)
+            },
+        },
+        MenuItem {
+            text: StaticString::new("Orientation"),
+            value: |_| StaticString::new(graphics::orientation().label()),
+            exec: |_| {
+                graphics::set_orientation(graphics::orientation().next());
+                settings::save();
+                Some(Action::ForceRedraw)
+            },
+        },
+        MenuItem {
+            text: StaticString::new("Swap buttons"),
+            value: |_| {
+                StaticString::new(if translator::swap_buttons() {
+                    "On"
+                } else {
+                    "Off"
+                })
+            },
+            exec: |_| {
+                translator::set_swap_buttons(!translator::swap_buttons());
+                settings::save();
+                Some(Action::ForceRedraw)
+            },
+        },
+    ]
+}
+
+fn subscription_value(kind: Subscription) -> StaticString<32> {
+    StaticString::new(if network2::subscribed(kind) {
+        "On"
+    } else {
+        "Off"
+    })
+}
+
+/// Flips a subscription and resubscribes, the core only reads the kinds when we subscribe.
+fn toggle_subscription(kind: Subscription) -> Option<Action> {
+    network2::set_subscribed(kind, !network2::subscribed(kind));
+    settings::save();
+    Some(Action::ReloadConnection)
+}
+
+fn subscription_items() -> [MenuItem; SUBSCRIPTIONS_SIZE] {
+    [
+        MenuItem {
+            text: StaticString::new("Back"),
+            value: |_| StaticString::empty(),
+            exec: |_| Some(Action::OpenMenu(MenuPage::Root)),
+        },
+        MenuItem {
+            text: StaticString::new(Subscription::Cues.label()),
+            value: |_| subscription_value(Subscription::Cues),
+            exec: |_| toggle_subscription(Subscription::Cues),
+        },
+        MenuItem {
+            text: StaticString::new(Subscription::Transport.label()),
+            value: |_| subscription_value(Subscription::Transport),
+            exec: |_| toggle_subscription(Subscription::Transport),
+        },
+        MenuItem {
+            text: StaticString::new(Subscription::Beats.label()),
+            value: |_| subscription_value(Subscription::Beats),
+            exec: |_| toggle_subscription(Subscription::Beats),
+        },
+        MenuItem {
+            text: StaticString::new(Subscription::Events.label()),
+            value: |_| subscription_value(Subscription::Events),
+            exec: |_| toggle_subscription(Subscription::Events),
+        },
+    ]
+}
+
+pub fn len(page: MenuPage) -> usize {
+    match page {
+        MenuPage::Root => ROOT_SIZE,
+        MenuPage::Display => DISPLAY_SIZE,
+        MenuPage::Subscriptions => SUBSCRIPTIONS_SIZE,
+    }
+}
+
+fn item(page: MenuPage, idx: usize) -> Option<MenuItem> {
+    match page {
+        MenuPage::Root => root_items().get(idx).copied(),
+        MenuPage::Display => display_items().get(idx).copied(),
+        MenuPage::Subscriptions => subscription_items().get(idx).copied(),
+    }
+}
+
+pub fn get_items_following_idx<const N: usize>(
+    page: MenuPage,
+    idx: usize,
+) -> [Option<MenuItem>; N] {
     let mut ret = [const { None }; N];
-    let items = items();
-    for (i, item) in ret.iter_mut().enumerate().take(n) {
-        *item = Some(items[idx + i]);
+    for (i, slot) in ret.iter_mut().enumerate() {
+        *slot = item(page, idx + i);
     }
     ret
 }
 
-pub fn get_item(idx: usize) -> MenuItem {
-    items()[idx]
+pub fn get_item(page: MenuPage, idx: usize) -> Option<MenuItem> {
+    item(page, idx)
 }
@@ -1,11 +1,18 @@
+use crate::diagnostics::{self, ChannelId};
 use crate::{events::Action, led::LED, LED_CH, METR_CH, UI_CH};
 use embassy_futures::select::{select, Either};
 use embassy_time::{Duration, Instant, Timer};
 
+pub const BEATS_PER_BAR: u8 = 4;
+/// The tempo range we keep to, whoever sets it. Much faster and the ticker never waits.
+pub const MIN_BPM: i64 = 20;
+pub const MAX_BPM: i64 = 400;
+
 #[embassy_executor::task]
 pub async fn metronome_task() {
     let mut bpm = 120;
     let mut last_blip = Instant::now();
+    let mut count = 0;
     UI_CH.send(Action::NewBPM(bpm as u64)).await;
 
     loop {
@@ -13,30 +20,34 @@ pub async fn metronome_task() {
             let msg = METR_CH.receive().await;
             match msg {
                 Action::MetronomeStart => {
+                    count = 0;
                     break 'stopped;
                 }
                 Action::MetronomeTempoTap => {
                     last_blip = Instant::now();
+                    count = 0;
                     break 'stopped;
                 }
                 Action::MetronomeAddTempo(t) => {
-                    bpm += t;
+                    bpm = bpm.saturating_add(t).clamp(MIN_BPM, MAX_BPM);
                     UI_CH.send(Action::NewBPM(bpm as u64)).await;
                 }
                 Action::MetronomeSetTempo(t) => {
-                    bpm = t;
+                    bpm = t.clamp(MIN_BPM, MAX_BPM);
                     UI_CH.send(Action::NewBPM(bpm as u64)).await;
                 }
-                Action::NewBeatData(data) => bpm = data.tempo() as i64,
+                Action::NewBeatData(data) => bpm = (data.tempo() as i64).clamp(MIN_BPM, MAX_BPM),
                 _ => {}
             }
         }
 
         'running: loop {
             let mut ticker =
-                embassy_time::Ticker::every(Duration::from_micros(60000000 / bpm.max(1) as u64));
+                embassy_time::Ticker::every(Duration::from_micros(60000000 / bpm as u64));
             'constant_tempo: loop {
-                LED_CH.try_send(Action::LEDBlip(LED::Metronome));
+                diagnostics::try_forward(&LED_CH, ChannelId::Led, Action::LEDBlip(LED::Metronome));
+                count = count % BEATS_PER_BAR + 1;
+                diagnostics::try_forward(&UI_CH, ChannelId::Ui, Action::MetronomeBeat(count));
                 'wait_blip: loop {
                     match select(ticker.next(), METR_CH.receive()).await {
                         Either::First(_) => break 'wait_blip,
@@ -48,7 +59,8 @@ pub async fn metronome_task() {
                             Action::MetronomeStop => break 'running,
                             Action::MetronomeTempoTap => {
                                 let now = Instant::now();
-                                bpm = (bpm + 60000000 / (now - last_blip).as_micros() as i64) / 2;
+                                let interval = (now - last_blip).as_micros().max(1) as i64;
+                                bpm = ((bpm + 60000000 / interval) / 2).clamp(MIN_BPM, MAX_BPM);
                                 last_blip = now;
                                 UI_CH.send(Action::NewBPM(bpm as u64)).await;
                                 break 'constant_tempo;
@@ -59,17 +71,17 @@ pub async fn metronome_task() {
                                 }
                             }
                             Action::MetronomeAddTempo(t) => {
-                                bpm += t;
+                                bpm = bpm.saturating_add(t).clamp(MIN_BPM, MAX_BPM);
                                 UI_CH.send(Action::NewBPM(bpm as u64)).await;
                                 break 'constant_tempo;
                             }
                             Action::MetronomeSetTempo(t) => {
-                                bpm = t;
+                                bpm = t.clamp(MIN_BPM, MAX_BPM);
                                 UI_CH.send(Action::NewBPM(bpm as u64)).await;
                                 break 'constant_tempo;
                             }
                             Action::NewBeatData(data) => {
-                                bpm = data.tempo() as i64;
+                                bpm = (data.tempo() as i64).clamp(MIN_BPM, MAX_BPM);
                                 break 'constant_tempo;
                             }
                             _ => {}
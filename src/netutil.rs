@@ -0,0 +1,12 @@
+use embassy_net::tcp::TcpSocket;
+
+/// Writes all of `data`, failing if the peer goes away first.
+pub async fn write_all(socket: &mut TcpSocket<'_>, mut data: &[u8]) -> Result<(), ()> {
+    while !data.is_empty() {
+        match socket.write(data).await {
+            Ok(0) | Err(_) => return Err(()),
+            Ok(n) => data = &data[n..],
+        }
+    }
+    Ok(())
+}
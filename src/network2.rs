@@ -5,8 +5,17 @@
 //! Note: the W55RP20 is a single package that contains both a RP2040 and the Wiznet W5500 ethernet
 //! controller
 
-use crate::events::Action;
+use crate::diagnostics;
+use crate::eventlog;
+use crate::events::{Action, Priority};
+use crate::firmware;
+use crate::identity;
 use crate::led::LED;
+use crate::logger::{self, Level};
+use crate::osc;
+use crate::state::{Core, LinkStatus, NetMode, SendStatus, SystemState, MAX_CORES};
+use crate::supervisor::{self, TaskId};
+use crate::ui;
 use crate::{ACTION_UPSTREAM, CONTROL_CH, LED_CH, STATE, UI_CH};
 use common::event::EventDescription;
 use common::mem::network::{IpAddress, SubscriberInfo};
@@ -15,17 +24,121 @@ use common::mem::typeflags::MessageType;
 use common::protocol::message::SmallMessage;
 use common::protocol::request::Request;
 use core::net::Ipv4Addr;
-use embassy_futures::select::{select, Either};
+use core::pin::pin;
+use embassy_futures::select::{select, select3, Either, Either3};
 use embassy_futures::yield_now;
-use embassy_net::udp::{PacketMetadata, SendError, UdpMetadata, UdpSocket};
-use embassy_net::{IpEndpoint, Stack};
+use embassy_net::udp::{PacketMetadata, SendError, UdpSocket};
+use embassy_net::{ConfigV4, DhcpConfig, IpEndpoint, Ipv4Cidr, Stack, StaticConfigV4};
 use embassy_net_wiznet::chip::W5500;
 use embassy_net_wiznet::*;
 use embassy_rp::gpio::{Input, Output};
 use embassy_rp::peripherals::PIO0;
 use embassy_rp::spi::Async;
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
-use embassy_time::{Duration, Timer, WithTimeout};
+use embassy_time::{Duration, Instant, Ticker, Timer, WithTimeout};
+use portable_atomic::{AtomicBool, AtomicU8, Ordering};
+
+const PING_INTERVAL: Duration = Duration::from_secs(10);
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(15);
+/// How often we check in with the supervisor while waiting on nothing but the user.
+const IDLE_BEAT: Duration = Duration::from_secs(1);
+
+static MASTER_CORE: AtomicU8 = AtomicU8::new(0);
+static MIRROR_CONTROL: AtomicBool = AtomicBool::new(false);
+
+/// Slot of the core the display follows while it is up. Takes effect on reconnect.
+pub fn master_core() -> usize {
+    MASTER_CORE.load(Ordering::Relaxed) as usize
+}
+
+pub fn set_master_core(slot: usize) {
+    MASTER_CORE.store(slot.min(MAX_CORES - 1) as u8, Ordering::Relaxed);
+}
+
+/// Whether control requests go to every core rather than just the one we follow.
+pub fn mirror_control() -> bool {
+    MIRROR_CONTROL.load(Ordering::Relaxed)
+}
+
+pub fn set_mirror_control(mirror: bool) {
+    MIRROR_CONTROL.store(mirror, Ordering::Relaxed);
+}
+
+/// Message kinds the core pushes that are worth choosing between. Shutdown notices are
+/// always subscribed, without them a core going away just looks like a stale link. Only
+/// kinds this firmware has a handler for are offered, asking for others would only cost
+/// bandwidth to be dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subscription {
+    Cues,
+    Transport,
+    Beats,
+    Events,
+}
+
+impl Subscription {
+    pub const ALL: [Subscription; 4] = [
+        Subscription::Cues,
+        Subscription::Transport,
+        Subscription::Beats,
+        Subscription::Events,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Subscription::Cues => "Cue data",
+            Subscription::Transport => "Transport",
+            Subscription::Beats => "Beat data",
+            Subscription::Events => "Events",
+        }
+    }
+
+    fn kind(&self) -> MessageType {
+        match self {
+            Subscription::Cues => MessageType::SmallCueData,
+            Subscription::Transport => MessageType::TransportData,
+            Subscription::Beats => MessageType::BeatData,
+            Subscription::Events => MessageType::EventOccured,
+        }
+    }
+
+    fn bit(&self) -> u8 {
+        1 << *self as u8
+    }
+}
+
+static SUBSCRIPTIONS: AtomicU8 = AtomicU8::new(0b1111);
+
+/// The enabled subscriptions as a bit per `Subscription`, as stored in settings.
+pub fn subscriptions() -> u8 {
+    SUBSCRIPTIONS.load(Ordering::Relaxed)
+}
+
+pub fn set_subscriptions(mask: u8) {
+    SUBSCRIPTIONS.store(mask & 0b1111, Ordering::Relaxed);
+}
+
+pub fn subscribed(kind: Subscription) -> bool {
+    subscriptions() & kind.bit() != 0
+}
+
+/// Takes effect on the next subscribe, which is the caller's to trigger.
+pub fn set_subscribed(kind: Subscription, on: bool) {
+    if on {
+        SUBSCRIPTIONS.fetch_or(kind.bit(), Ordering::Relaxed);
+    } else {
+        SUBSCRIPTIONS.fetch_and(!kind.bit(), Ordering::Relaxed);
+    }
+}
+
+fn message_kinds() -> MessageType {
+    Subscription::ALL
+        .iter()
+        .filter(|kind| subscribed(**kind))
+        .fold(MessageType::ShutdownOccured, |kinds, kind| {
+            kinds | kind.kind()
+        })
+}
 
 pub type SpiType = embassy_rp::pio_programs::spi::Spi<'static, PIO0, 0, Async>;
 pub type SpiBusType = embassy_embedded_hal::shared_bus::asynch::spi::SpiDevice<
@@ -48,14 +161,49 @@ pub async fn net_task(mut runner: embassy_net::Runner<'static, Device<'static>>)
 }
 
 async fn wait_for_config(stack: Stack<'static>) -> embassy_net::StaticConfigV4 {
+    let mut status = None;
     loop {
+        supervisor::beat(TaskId::Network);
         if let Some(config) = stack.config_v4() {
             return config.clone();
         }
+        let now = if stack.is_link_up() {
+            LinkStatus::DhcpPending
+        } else {
+            LinkStatus::NoLink
+        };
+        if status != Some(now) {
+            status = Some(now);
+            set_link(now).await;
+        }
         yield_now().await;
     }
 }
 
+/// Returns whether the status changed.
+async fn set_link(status: LinkStatus) -> bool {
+    let mut state = STATE.lock().await;
+    if state.link == status {
+        return false;
+    }
+    state.link = status;
+    drop(state);
+    logger::log(Level::Info, "net", format_args!("link: {}", status.label()));
+    ACTION_UPSTREAM
+        .send(Action::LinkStatusChanged(status))
+        .await;
+    true
+}
+
+/// The core we follow shut down and none can take over. Blanks what it last told us rather
+/// than leave a cue and tempo up that look current.
+async fn core_offline() {
+    STATE.lock().await.clear_core_data();
+    ui::notify(Priority::Critical, "Core offline");
+    ACTION_UPSTREAM.send(Action::LoseConnection).await;
+    ACTION_UPSTREAM.send(Action::ForceRedraw).await;
+}
+
 #[embassy_executor::task]
 pub async fn stack_task(stack: Stack<'static>) {
     loop {
@@ -64,15 +212,36 @@ pub async fn stack_task(stack: Stack<'static>) {
         let mut state = STATE.lock().await;
         state.self_ip = IpAddress::new(cfg.address.address().octets(), 1234);
         let self_ip = state.self_ip;
-        let endpoint = IpEndpoint::new(
-            embassy_net::IpAddress::Ipv4(Ipv4Addr::new(
-                state.core_ip.addr[0],
-                state.core_ip.addr[1],
-                state.core_ip.addr[2],
-                state.core_ip.addr[3],
-            )),
-            state.core_ip.port,
-        );
+        let mut endpoints: [Option<IpEndpoint>; MAX_CORES] = [None; MAX_CORES];
+        for (ep, core) in endpoints.iter_mut().zip(state.cores.iter_mut()) {
+            core.last_heartbeat = None;
+            core.ping_sent = None;
+            core.rtt_ms = None;
+            core.last_send = SendStatus::Idle;
+            core.offline = false;
+            if core.configured() {
+                *ep = Some(to_endpoint(core.ip));
+            }
+        }
+        // Every reconnect starts over from the master
+        let mut active = if endpoints[master_core()].is_some() {
+            master_core()
+        } else {
+            endpoints.iter().position(Option::is_some).unwrap_or(0)
+        };
+        state.active_core = active;
+        let mut endpoint = endpoints[active].unwrap_or(to_endpoint(state.cores[0].ip));
+        let device_name: StaticString<32> = StaticString::new(state.device_name());
+        let log_host = if state.log_host.addr == [0, 0, 0, 0] {
+            None
+        } else {
+            Some(to_endpoint(state.log_host))
+        };
+        let osc_target = if state.osc_host.addr == [0, 0, 0, 0] {
+            None
+        } else {
+            Some(to_endpoint(state.osc_host))
+        };
         drop(state);
         ACTION_UPSTREAM.send(Action::ForceRedraw).await;
 
@@ -92,42 +261,118 @@ pub async fn stack_task(stack: Stack<'static>) {
         );
         socket.bind(1234).unwrap();
 
+        let mut osc_rx_buffer = [0; 1024];
+        let mut osc_tx_buffer = [0; 512];
+        let mut osc_rx_meta = [PacketMetadata::EMPTY; 4];
+        let mut osc_tx_meta = [PacketMetadata::EMPTY; 4];
+        let mut osc_buf = [0; 512];
+        let mut osc_socket = UdpSocket::new(
+            stack,
+            &mut osc_rx_meta,
+            &mut osc_rx_buffer,
+            &mut osc_tx_meta,
+            &mut osc_tx_buffer,
+        );
+        osc_socket.bind(osc::OSC_PORT).unwrap();
+
         LED_CH.send(Action::LEDBlip(LED::Connection)).await;
-        if let Err(_err) = send_request(
-            Request::Subscribe(SubscriberInfo {
-                identifier: StaticString::new("ClicKS Hardware Controller"),
-                address: self_ip,
-                message_kinds: MessageType::ShutdownOccured
-                    | MessageType::BeatData
-                    | MessageType::EventOccured
-                    | MessageType::TransportData
-                    | MessageType::SmallCueData,
-                last_contact: 0,
-            }),
-            endpoint,
-            &socket,
-        )
-        .await
-        {
+        let subscribe = Request::Subscribe(SubscriberInfo {
+            identifier: StaticString::new(device_name.str()),
+            address: self_ip,
+            message_kinds: message_kinds(),
+            last_contact: 0,
+        });
+        // Backups are subscribed too, so they are already feeding us if we fail over
+        let mut subscribed = 0;
+        for ep in endpoints.iter().flatten() {
+            if send_request(subscribe, *ep, &socket).await.is_ok() {
+                subscribed += 1;
+            }
+        }
+        if subscribed == 0 {
+            logger::log(Level::Error, "net", format_args!("subscribe failed"));
+            ui::notify(Priority::Critical, "Core unreachable");
+            // OSC doesn't need the core, keep it going while we wait
+            let mut serve = pin!(osc::serve(&osc_socket, osc_target, &mut osc_buf));
+            let mut beat = Ticker::every(IDLE_BEAT);
             loop {
-                if let Action::ReloadConnection = CONTROL_CH.receive().await {
+                supervisor::beat(TaskId::Network);
+                if let Either3::First(Action::ReloadConnection) =
+                    select3(CONTROL_CH.receive(), serve.as_mut(), beat.next()).await
+                {
                     break;
                 }
             }
         } else {
-            let _ = send_request(Request::Ping, endpoint, &socket).await;
+            ping_all(&endpoints, subscribe, &socket).await;
             LED_CH.send(Action::LEDSet(LED::Connection, true)).await;
+            log_reset_report().await;
+            logger::log(
+                Level::Info,
+                "net",
+                format_args!(
+                    "subscribed to {} of {} cores",
+                    subscribed,
+                    endpoints.iter().flatten().count()
+                ),
+            );
+            set_link(LinkStatus::Subscribed).await;
+            firmware::confirm_boot();
+            let subscribed_at = Instant::now();
+            let mut last_ping = subscribed_at;
+            let mut check_now = false;
             loop {
+                supervisor::beat(TaskId::Network);
+                let now = Instant::now();
+                if check_now || now - last_ping >= PING_INTERVAL {
+                    check_now = false;
+                    last_ping = now;
+                    ping_all(&endpoints, subscribe, &socket).await;
+                    let (link, failover) =
+                        check_cores(&endpoints, active, subscribed_at, now).await;
+                    if let Some(slot) = failover {
+                        active = slot;
+                        endpoint = endpoints[slot].unwrap_or(endpoint);
+                        STATE.lock().await.active_core = slot;
+                        logger::log(
+                            Level::Warn,
+                            "net",
+                            format_args!("failing over to {} core {}", Core::role(slot), endpoint),
+                        );
+                        let mut msg_buf = [0u8; 32];
+                        let msg = format_no_std::show(
+                            &mut msg_buf,
+                            format_args!("Failover to {}", Core::role(slot)),
+                        )
+                        .unwrap_or_default();
+                        ui::notify(Priority::Critical, msg);
+                        ACTION_UPSTREAM.send(Action::ForceRedraw).await;
+                    }
+                    if set_link(link).await && link == LinkStatus::Offline {
+                        core_offline().await;
+                    }
+                }
+                if let Some(host) = log_host {
+                    flush_logs(host, &socket).await;
+                }
+                osc::service(&osc_socket, osc_target, &mut osc_buf).await;
                 buf.fill(0);
                 // Handle network receives
-                if let Ok(Ok((n, _ep))) = socket
+                if let Ok(Ok((_, ep))) = socket
                     .recv_from(&mut buf)
                     .with_timeout(Duration::from_millis(10))
                     .await
                 {
                     let res = postcard::from_bytes(&buf[1..41]);
+                    diagnostics::record_rx(res.is_ok());
                     if let Ok(msg) = res {
-                        receive_message(msg).await;
+                        // Cores are told apart by address, they may answer from any port
+                        let from = endpoints
+                            .iter()
+                            .position(|e| e.is_some_and(|e| e.addr == ep.endpoint.addr))
+                            .unwrap_or(active);
+                        // Look for a backup straight away rather than at the next ping
+                        check_now |= receive_message(msg, &buf[1..], from, active).await;
                     }
                 }
 
@@ -139,13 +384,58 @@ pub async fn stack_task(stack: Stack<'static>) {
                 {
                     match action {
                         Action::RequestToCore(request) => {
-                            if let Err(err) = send_request(request, endpoint, &socket).await {
+                            let control = matches!(request, Request::ControlAction(_));
+                            let mirror = control && mirror_control();
+                            let mut failed = false;
+                            for (slot, ep) in endpoints.iter().enumerate() {
+                                let Some(ep) = ep else {
+                                    continue;
+                                };
+                                if slot != active && !mirror {
+                                    continue;
+                                }
+                                let res = send_request(request, *ep, &socket).await;
+                                if control {
+                                    STATE.lock().await.cores[slot].last_send = match res {
+                                        Ok(()) => SendStatus::Sent,
+                                        Err(_) => SendStatus::Failed,
+                                    };
+                                }
+                                if let Err(err) = res {
+                                    logger::log(
+                                        Level::Warn,
+                                        "net",
+                                        format_args!(
+                                            "request to {} failed: {:?}",
+                                            Core::role(slot),
+                                            err
+                                        ),
+                                    );
+                                    if slot == active {
+                                        failed = true;
+                                    } else {
+                                        let mut buf = [0u8; 32];
+                                        let msg = format_no_std::show(
+                                            &mut buf,
+                                            format_args!("Send to {} failed", Core::role(slot)),
+                                        )
+                                        .unwrap_or_default();
+                                        ui::notify(Priority::Warning, msg);
+                                    }
+                                }
+                            }
+                            // Only losing the core we follow calls for a reconnect
+                            if failed {
+                                ui::notify(Priority::Warning, "Request failed");
                                 break;
                             }
                         }
                         Action::ReloadConnection => {
                             break;
                         }
+                        // Goes out with the logs, the core has no request for it
+                        Action::SendDiagnostics if log_host.is_some() => diagnostics::send_report(),
+                        Action::SendDiagnostics => ui::notify(Priority::Warning, "No log host set"),
                         _ => {}
                     }
                 }
@@ -153,11 +443,122 @@ pub async fn stack_task(stack: Stack<'static>) {
         }
 
         LED_CH.send(Action::LEDSet(LED::Connection, false)).await;
+        set_link(LinkStatus::NoLink).await;
         socket.close();
+        osc_socket.close();
+    }
+}
+
+/// Works out the link status of the core we follow, and which core to fail over to if it
+/// has gone quiet and another hasn't. We don't fail back on our own: swapping cores in the
+/// middle of a show is worse than staying on a healthy backup until someone reconnects.
+async fn check_cores(
+    endpoints: &[Option<IpEndpoint>; MAX_CORES],
+    active: usize,
+    subscribed_at: Instant,
+    now: Instant,
+) -> (LinkStatus, Option<usize>) {
+    let state = STATE.lock().await;
+    let alive = |slot: usize| {
+        let heard = state.cores[slot]
+            .last_heartbeat
+            .unwrap_or(subscribed_at)
+            .max(subscribed_at);
+        endpoints[slot].is_some() && !state.cores[slot].offline && now - heard <= HEARTBEAT_TIMEOUT
+    };
+    if alive(active) {
+        return (LinkStatus::Subscribed, None);
+    }
+    match (0..MAX_CORES).find(|slot| alive(*slot)) {
+        Some(slot) => (LinkStatus::Subscribed, Some(slot)),
+        None if state.cores[active].offline => (LinkStatus::Offline, None),
+        None => (LinkStatus::Stale, None),
     }
 }
 
-async fn receive_message(msg: SmallMessage) {
+/// Pings every core. A core that shut down forgot us, so it gets `subscribe` again instead
+/// and starts sending heartbeats once it is back.
+async fn ping_all(
+    endpoints: &[Option<IpEndpoint>; MAX_CORES],
+    subscribe: Request,
+    socket: &UdpSocket<'_>,
+) {
+    for (slot, ep) in endpoints.iter().enumerate() {
+        let Some(ep) = ep else {
+            continue;
+        };
+        let mut state = STATE.lock().await;
+        let core = &mut state.cores[slot];
+        let request = if core.offline {
+            subscribe
+        } else {
+            core.ping_sent = Some(Instant::now());
+            Request::Ping
+        };
+        drop(state);
+        let _ = send_request(request, *ep, socket).await;
+    }
+}
+
+/// Returns true when the core we follow has just shut down. `wire` is the message as it
+/// arrived, it starts with the variant numbers that are all we can say about kinds newer
+/// than us.
+async fn receive_message(msg: SmallMessage, wire: &[u8], from: usize, active: usize) -> bool {
+    if let SmallMessage::Heartbeat(_) = msg {
+        let now = Instant::now();
+        let mut state = STATE.lock().await;
+        let core = &mut state.cores[from];
+        core.last_heartbeat = Some(now);
+        // A core answers a ping with a heartbeat, so the first one after it times the trip
+        if let Some(sent) = core.ping_sent.take() {
+            core.rtt_ms = Some((now - sent).as_millis() as u32);
+        }
+        let came_back = core.offline;
+        core.offline = false;
+        let was_down = matches!(state.link, LinkStatus::Stale | LinkStatus::Offline);
+        drop(state);
+        if came_back {
+            logger::log(
+                Level::Info,
+                "net",
+                format_args!("{} core is back", Core::role(from)),
+            );
+        }
+        if from == active {
+            if was_down {
+                set_link(LinkStatus::Subscribed).await;
+            }
+            if came_back {
+                ui::notify(Priority::Info, "Core back online");
+                ACTION_UPSTREAM.send(Action::GainConnection).await;
+                ACTION_UPSTREAM.send(Action::ForceRedraw).await;
+            }
+            LED_CH.send(Action::LEDSet(LED::Connection, true)).await;
+        }
+        return false;
+    }
+    if let SmallMessage::ShutdownOccured = msg {
+        let mut state = STATE.lock().await;
+        let core = &mut state.cores[from];
+        core.offline = true;
+        core.last_heartbeat = None;
+        core.ping_sent = None;
+        core.rtt_ms = None;
+        drop(state);
+        logger::log(
+            Level::Warn,
+            "net",
+            format_args!("{} core shut down", Core::role(from)),
+        );
+        if from == active {
+            LED_CH.send(Action::LEDSet(LED::Connection, false)).await;
+        }
+        return from == active;
+    }
+    // Backups run the show in parallel, only the core we follow drives the display
+    if from != active {
+        return false;
+    }
     match msg {
         SmallMessage::CueData(data) => {
             ACTION_UPSTREAM
@@ -168,22 +569,131 @@ async fn receive_message(msg: SmallMessage) {
             ACTION_UPSTREAM.send(Action::NewTransportData(data)).await;
         }
         SmallMessage::BeatData(data) => {
+            STATE.lock().await.beat = data.beat;
             LED_CH.send(Action::NewBeatData(data.beat)).await;
             UI_CH.send(Action::NewBeatData(data.beat)).await;
         }
-        SmallMessage::EventOccured(event) => match event {
-            EventDescription::RehearsalMarkEvent { label } => {
-                UI_CH.send(Action::NewLabelData(label)).await;
+        SmallMessage::EventOccured(event) => {
+            let text = eventlog::describe(&event, wire[1]);
+            let mut state = STATE.lock().await;
+            let cue_idx = state.cue_idx;
+            state.events.push(eventlog::Entry {
+                at: Instant::now(),
+                cue_idx,
+                text,
+            });
+            drop(state);
+            logger::log(
+                Level::Info,
+                "event",
+                format_args!("{} in cue {}", text.str(), cue_idx),
+            );
+            match event {
+                // The main screen shows the mark, that's notice enough
+                EventDescription::RehearsalMarkEvent { label } => {
+                    UI_CH.send(Action::NewLabelData(label)).await;
+                }
+                _ => {
+                    ui::notify(Priority::Info, text.str());
+                    LED_CH.send(Action::LEDBlip(LED::VLT)).await;
+                }
             }
-            _ => {}
-        },
-        SmallMessage::ShutdownOccured => {
-            LED_CH.send(Action::LEDSet(LED::Connection, false)).await;
         }
-        SmallMessage::Heartbeat(data) => {
-            LED_CH.send(Action::LEDSet(LED::Connection, true)).await;
+        // Heartbeats and shutdowns are dealt with above. We never subscribe to anything else,
+        // so this is a core answering with a kind newer than this firmware
+        _ => {
+            logger::log(
+                Level::Debug,
+                "net",
+                format_args!(
+                    "unhandled message kind {} from {}",
+                    wire[0],
+                    Core::role(from)
+                ),
+            );
+        }
+    }
+    false
+}
+
+/// DHCP, asking to be known by the device name.
+pub fn dhcp_config(name: &str) -> DhcpConfig {
+    let mut config = DhcpConfig::default();
+    let host = identity::hostname(name);
+    config.hostname = (!host.is_empty()).then_some(host);
+    config
+}
+
+/// Switches the stack between DHCP and the static address in `state`.
+pub fn apply_net_mode(stack: Stack<'static>, state: &SystemState) {
+    stack.set_config_v4(config_v4(state));
+}
+
+/// The stack configuration to start up with.
+pub fn net_config(state: &SystemState) -> embassy_net::Config {
+    match config_v4(state) {
+        ConfigV4::Static(config) => embassy_net::Config::ipv4_static(config),
+        _ => embassy_net::Config::dhcpv4(dhcp_config(state.device_name())),
+    }
+}
+
+fn config_v4(state: &SystemState) -> ConfigV4 {
+    match state.net_mode {
+        NetMode::Dhcp => ConfigV4::Dhcp(dhcp_config(state.device_name())),
+        NetMode::Static => {
+            let [a, b, c, d] = state.static_ip.addr;
+            let [e, f, g, h] = state.gateway.addr;
+            ConfigV4::Static(StaticConfigV4 {
+                address: Ipv4Cidr::new(Ipv4Addr::new(a, b, c, d), state.static_prefix),
+                gateway: (state.gateway.addr != [0, 0, 0, 0]).then(|| Ipv4Addr::new(e, f, g, h)),
+                dns_servers: Default::default(),
+            })
+        }
+    }
+}
+
+fn to_endpoint(ip: IpAddress) -> IpEndpoint {
+    IpEndpoint::new(
+        embassy_net::IpAddress::Ipv4(Ipv4Addr::new(
+            ip.addr[0], ip.addr[1], ip.addr[2], ip.addr[3],
+        )),
+        ip.port,
+    )
+}
+
+/// Sends a few buffered log records per call, so a backlog after reconnecting doesn't
+/// hold up message handling.
+async fn flush_logs(endpoint: IpEndpoint, socket: &UdpSocket<'_>) {
+    for _ in 0..4 {
+        let Some(record) = logger::pop() else {
+            return;
+        };
+        let mut buf = [0u8; 128];
+        let line = record.to_syslog(&mut buf);
+        let res = socket.send_to(line.as_bytes(), endpoint).await;
+        diagnostics::record_tx(res.is_ok());
+    }
+}
+
+/// Logs once per boot why we came up, if it wasn't a clean power on. Waits until we are
+/// connected, so it isn't pushed out of the log buffer by everything logged while starting.
+async fn log_reset_report() {
+    let report = {
+        let mut state = STATE.lock().await;
+        match state.reset_report.as_mut() {
+            Some(r) if r.is_crash() && !r.reported => {
+                r.reported = true;
+                Some(*r)
+            }
+            _ => None,
         }
-        _ => {}
+    };
+    if let Some(report) = report {
+        logger::send(
+            Level::Error,
+            "reset",
+            format_args!("{}: {}", report.short().str(), report.message.str()),
+        );
     }
 }
 
@@ -193,9 +703,11 @@ async fn send_request(
     socket: &UdpSocket<'_>,
 ) -> Result<(), SendError> {
     let mut buf = [0; 4096];
-    if let Ok(send_buf) = postcard::to_slice(&req, &mut buf) {
+    let res = if let Ok(send_buf) = postcard::to_slice(&req, &mut buf) {
         socket.send_to(send_buf, endpoint).await
     } else {
         Err(SendError::PacketTooLarge)
-    }
+    };
+    diagnostics::record_tx(res.is_ok());
+    res
 }
@@ -0,0 +1,241 @@
+use crate::diagnostics;
+use crate::events::{Action, ButtonId};
+use crate::led::LED;
+use crate::logger::{self, Level};
+use crate::metronome;
+use crate::{ACTION_UPSTREAM, LED_CH, STATE};
+use common::cue::CueMetadata;
+use common::mem::str::StaticString;
+use embassy_futures::select::{select, Either};
+use embassy_net::udp::{UdpMetadata, UdpSocket};
+use embassy_net::IpEndpoint;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_time::{Duration, WithTimeout};
+
+/// Where we listen for OSC, the usual port for consoles sending to a third party.
+pub const OSC_PORT: u16 = 8000;
+const RECV_TIMEOUT: Duration = Duration::from_millis(5);
+
+static PRESSES: Channel<CriticalSectionRawMutex, ButtonId, 4> = Channel::new();
+
+/// Queues a button press to be sent as OSC. Presses are dropped if nobody is sending them.
+pub fn button_pressed(id: ButtonId) {
+    let _ = PRESSES.try_send(id);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arg<'a> {
+    Int(i32),
+    Float(f32),
+    Str(&'a str),
+    Bool(bool),
+}
+
+impl Arg<'_> {
+    fn as_int(&self) -> Option<i64> {
+        match *self {
+            Arg::Int(i) => Some(i as i64),
+            Arg::Float(f) => Some((f + 0.5) as i64),
+            Arg::Bool(b) => Some(b as i64),
+            Arg::Str(s) => s.parse().ok(),
+        }
+    }
+}
+
+/// A single OSC message. Bundles are not taken apart; nothing we talk to sends them for
+/// the addresses we handle.
+pub struct Message<'a> {
+    pub address: &'a str,
+    tags: &'a [u8],
+    data: &'a [u8],
+}
+
+/// Splits a null terminated string padded to four bytes off the front of `buf`.
+fn take_str(buf: &[u8]) -> Option<(&str, &[u8])> {
+    let end = buf.iter().position(|b| *b == 0)?;
+    let s = core::str::from_utf8(&buf[..end]).ok()?;
+    let padded = (end + 4) & !3;
+    Some((s, buf.get(padded..)?))
+}
+
+fn take_u32(buf: &[u8]) -> Option<(u32, &[u8])> {
+    let bytes = buf.get(..4)?;
+    Some((
+        u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        &buf[4..],
+    ))
+}
+
+impl<'a> Message<'a> {
+    pub fn parse(buf: &'a [u8]) -> Option<Self> {
+        let (address, rest) = take_str(buf)?;
+        if !address.starts_with('/') {
+            return None;
+        }
+        // Very old senders leave the type tags out altogether
+        let (tags, data) = match take_str(rest) {
+            Some((tags, data)) if tags.starts_with(',') => (&tags.as_bytes()[1..], data),
+            _ => (&[][..], rest),
+        };
+        Some(Self {
+            address,
+            tags,
+            data,
+        })
+    }
+
+    /// The arguments in order, stopping at the first one of a type we don't read.
+    pub fn args(&self) -> impl Iterator<Item = Arg<'a>> {
+        let mut tags = self.tags.iter();
+        let mut data = self.data;
+        core::iter::from_fn(move || {
+            let (arg, rest) = match tags.next()? {
+                b'i' => take_u32(data).map(|(v, rest)| (Arg::Int(v as i32), rest))?,
+                b'f' => take_u32(data).map(|(v, rest)| (Arg::Float(f32::from_bits(v)), rest))?,
+                b's' => take_str(data).map(|(s, rest)| (Arg::Str(s), rest))?,
+                b'T' => (Arg::Bool(true), data),
+                b'F' => (Arg::Bool(false), data),
+                _ => return None,
+            };
+            data = rest;
+            Some(arg)
+        })
+    }
+
+    pub fn arg(&self, n: usize) -> Option<Arg<'a>> {
+        self.args().nth(n)
+    }
+}
+
+/// Writes a message with a single int argument into `buf`.
+pub fn encode<'b>(buf: &'b mut [u8], address: &str, value: i32) -> Option<&'b [u8]> {
+    let mut len = 0;
+    for part in [address.as_bytes(), b",i".as_slice()] {
+        let padded = (part.len() + 4) & !3;
+        let field = buf.get_mut(len..len + padded)?;
+        field.fill(0);
+        field[..part.len()].copy_from_slice(part);
+        len += padded;
+    }
+    buf.get_mut(len..len + 4)?
+        .copy_from_slice(&value.to_be_bytes());
+    len += 4;
+    Some(&buf[..len])
+}
+
+fn led(name: &str) -> Option<LED> {
+    Some(match name {
+        "connection" => LED::Connection,
+        "metronome" => LED::Metronome,
+        "playing" => LED::Playing,
+        "vlt" => LED::VLT,
+        _ => return None,
+    })
+}
+
+/// Acts on an incoming message. Returns false for addresses we don't know.
+async fn handle(msg: &Message<'_>) -> bool {
+    let Some(path) = msg.address.strip_prefix("/clicks/") else {
+        return false;
+    };
+    match path {
+        "bpm" => {
+            let Some(bpm) = msg.arg(0).and_then(|a| a.as_int()).filter(|b| *b > 0) else {
+                return false;
+            };
+            let bpm = bpm.clamp(metronome::MIN_BPM, metronome::MAX_BPM);
+            ACTION_UPSTREAM.send(Action::MetronomeSetTempo(bpm)).await;
+        }
+        "cue" => {
+            let Some(idx) = msg.arg(0).and_then(|a| a.as_int()) else {
+                return false;
+            };
+            let mut cue = CueMetadata::const_default();
+            if let Some(Arg::Str(name)) = msg.arg(1) {
+                cue.human_ident = StaticString::new(name);
+            }
+            ACTION_UPSTREAM
+                .send(Action::NewCueData(
+                    idx.clamp(0, u16::MAX as i64) as u16,
+                    cue,
+                ))
+                .await;
+        }
+        "mark" => {
+            let Some(Arg::Str(label)) = msg.arg(0) else {
+                return false;
+            };
+            ACTION_UPSTREAM
+                .send(Action::NewLabelData(StaticString::new(label)))
+                .await;
+        }
+        "metronome/start" => ACTION_UPSTREAM.send(Action::MetronomeStart).await,
+        "metronome/stop" => ACTION_UPSTREAM.send(Action::MetronomeStop).await,
+        _ => {
+            let Some(led) = path.strip_prefix("led/").and_then(led) else {
+                return false;
+            };
+            let on = msg.arg(0).and_then(|a| a.as_int()).unwrap_or(1) != 0;
+            LED_CH.send(Action::LEDSet(led, on)).await;
+        }
+    }
+    true
+}
+
+/// Handles one incoming message if there is one, and sends any queued button presses to
+/// `target`. Called from the network loop alongside the core subscription.
+pub async fn service(socket: &UdpSocket<'_>, target: Option<IpEndpoint>, buf: &mut [u8]) {
+    if let Ok(Ok((n, ep))) = socket.recv_from(buf).with_timeout(RECV_TIMEOUT).await {
+        receive(&buf[..n], ep).await;
+    }
+    while let Ok(id) = PRESSES.try_receive() {
+        send_press(socket, target, id).await;
+    }
+}
+
+/// Handles messages and presses as they come, for as long as it is polled. For when there
+/// is no core to share the network loop with.
+pub async fn serve(socket: &UdpSocket<'_>, target: Option<IpEndpoint>, buf: &mut [u8]) {
+    loop {
+        match select(socket.recv_from(buf), PRESSES.receive()).await {
+            Either::First(Ok((n, ep))) => receive(&buf[..n], ep).await,
+            Either::First(Err(_)) => {}
+            Either::Second(id) => send_press(socket, target, id).await,
+        }
+    }
+}
+
+async fn receive(datagram: &[u8], from: UdpMetadata) {
+    let handled = match Message::parse(datagram) {
+        Some(msg) => handle(&msg).await,
+        None => false,
+    };
+    diagnostics::record_rx(handled);
+    if !handled {
+        logger::log(
+            Level::Debug,
+            "osc",
+            format_args!("ignored message from {}", from.endpoint),
+        );
+    }
+}
+
+async fn send_press(socket: &UdpSocket<'_>, target: Option<IpEndpoint>, id: ButtonId) {
+    let Some(target) = target else {
+        return;
+    };
+    let configured = STATE.lock().await.osc_addresses[id as usize];
+    let mut addr_buf = [0u8; 48];
+    let address = if configured.len() == 0 {
+        format_no_std::show(&mut addr_buf, format_args!("/clicks/button/{}", id.name()))
+            .unwrap_or_default()
+    } else {
+        configured.str()
+    };
+    let mut msg_buf = [0u8; 64];
+    if let Some(msg) = encode(&mut msg_buf, address, 1) {
+        let res = socket.send_to(msg, target).await;
+        diagnostics::record_tx(res.is_ok());
+    }
+}
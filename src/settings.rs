@@ -0,0 +1,284 @@
+use crate::events::{Action, Priority};
+use crate::graphics::{self, Contrast, IdleTimeout, Orientation, ScrollSpeed, LOGO_LEN};
+use crate::layout::{self, Layout};
+use crate::led;
+use crate::logger::{self, Level};
+use crate::network2;
+use crate::state::{NetMode, SystemState, MAX_CORES};
+use crate::translator;
+use crate::ui;
+use crate::{ACTION_UPSTREAM, STATE};
+use common::mem::str::StaticString;
+use core::cell::RefCell;
+use embassy_futures::select::{select, Either};
+use embassy_rp::flash::{Blocking, Error, Flash, ERASE_SIZE};
+use embassy_rp::peripherals::FLASH;
+use embassy_sync::blocking_mutex::raw::{CriticalSectionRawMutex, ThreadModeRawMutex};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::channel::Channel;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, WithTimeout};
+
+pub const FLASH_SIZE: usize = 2 * 1024 * 1024;
+// Last sector of flash, well clear of the program image
+const SETTINGS_OFFSET: u32 = (FLASH_SIZE - ERASE_SIZE) as u32;
+const SETTINGS_MAGIC: [u8; 4] = *b"CKS\x01";
+// Uploaded Lock screen logo, in the sector before the settings
+const LOGO_OFFSET: u32 = SETTINGS_OFFSET - ERASE_SIZE as u32;
+const LOGO_MAGIC: [u8; 4] = *b"LOGO";
+const RECORD_LEN: usize = 512;
+// One length prefixed field of 33 bytes per button
+const OSC_OFFSET: usize = 92;
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+pub type SettingsFlash = Flash<'static, FLASH, Blocking, FLASH_SIZE>;
+/// The one flash driver, shared between settings and firmware updates. Only tasks on the
+/// thread mode executor use it, so holding it through an erase doesn't mask interrupts.
+pub type SharedFlash = Mutex<ThreadModeRawMutex, RefCell<SettingsFlash>>;
+
+static SAVE: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+static LOGO_UPLOAD: Channel<CriticalSectionRawMutex, Option<[u8; LOGO_LEN]>, 1> = Channel::new();
+
+/// Schedules the current settings to be written to flash. A burst of changes from the menu
+/// is written once, after it settles.
+pub fn save() {
+    SAVE.signal(());
+}
+
+// Unused bytes stay erased (0xFF), which `apply` skips as out of range
+fn encode(state: &SystemState) -> [u8; RECORD_LEN] {
+    let mut buf = [0xFF; RECORD_LEN];
+    buf[..4].copy_from_slice(&SETTINGS_MAGIC);
+    buf[4] = layout::layout() as u8;
+    buf[5] = graphics::contrast() as u8;
+    buf[6] = graphics::idle_timeout() as u8;
+    buf[7] = graphics::scroll_speed() as u8;
+    buf[8] = graphics::orientation() as u8;
+    buf[9] = translator::swap_buttons() as u8;
+    buf[10] = network2::subscriptions();
+    // Length first, an empty name stands for the default
+    put_str(&mut buf[11..44], state.device_name.str());
+    buf[44] = state.net_mode as u8;
+    buf[45..49].copy_from_slice(&state.static_ip.addr);
+    buf[49] = state.static_prefix;
+    buf[50..54].copy_from_slice(&state.gateway.addr);
+    buf[54] = led::level();
+    // All cores of a show listen on the same port
+    buf[55..57].copy_from_slice(&state.cores[0].ip.port.to_le_bytes());
+    for (slot, core) in state.cores.iter().enumerate() {
+        buf[57 + slot * 4..61 + slot * 4].copy_from_slice(&core.ip.addr);
+    }
+    buf[73..77].copy_from_slice(&state.log_host.addr);
+    buf[77..81].copy_from_slice(&state.osc_host.addr);
+    buf[81..83].copy_from_slice(&state.osc_host.port.to_le_bytes());
+    put_str(&mut buf[83..92], state.web_pin.str());
+    for (i, address) in state.osc_addresses.iter().enumerate() {
+        put_str(
+            &mut buf[OSC_OFFSET + i * 33..OSC_OFFSET + (i + 1) * 33],
+            address.str(),
+        );
+    }
+    buf[488] = logger::level() as u8;
+    buf[489] = network2::master_core() as u8;
+    buf[490] = network2::mirror_control() as u8;
+    buf
+}
+
+fn apply(buf: &[u8; RECORD_LEN], state: &mut SystemState) {
+    if let Some(l) = Layout::ALL.get(buf[4] as usize) {
+        layout::set_layout(*l);
+    }
+    if let Some(c) = Contrast::ALL.get(buf[5] as usize) {
+        graphics::set_contrast(*c);
+    }
+    if let Some(t) = IdleTimeout::ALL.get(buf[6] as usize) {
+        graphics::set_idle_timeout(*t);
+    }
+    if let Some(s) = ScrollSpeed::ALL.get(buf[7] as usize) {
+        graphics::set_scroll_speed(*s);
+    }
+    if let Some(o) = Orientation::ALL.get(buf[8] as usize) {
+        graphics::set_orientation(*o);
+    }
+    if buf[9] <= 1 {
+        translator::set_swap_buttons(buf[9] == 1);
+    }
+    if buf[10] <= 0b1111 {
+        network2::set_subscriptions(buf[10]);
+    }
+    if let Some(name) = get_str(&buf[11..44]) {
+        state.device_name = StaticString::new(name);
+    }
+    if let Some(mode) = NetMode::ALL.get(buf[44] as usize) {
+        state.net_mode = *mode;
+    }
+    if let Some(addr) = get_addr(&buf[45..49]) {
+        state.static_ip.addr = addr;
+    }
+    if buf[49] <= 32 {
+        state.static_prefix = buf[49];
+    }
+    if let Some(addr) = get_addr(&buf[50..54]) {
+        state.gateway.addr = addr;
+    }
+    if buf[54] <= 100 {
+        led::set_level(buf[54]);
+    }
+    if let Some(port) = get_port(&buf[55..57]) {
+        for core in state.cores.iter_mut() {
+            core.ip.port = port;
+        }
+    }
+    for (slot, core) in state.cores.iter_mut().enumerate() {
+        if let Some(addr) = get_addr(&buf[57 + slot * 4..61 + slot * 4]) {
+            core.ip.addr = addr;
+        }
+    }
+    if let Some(addr) = get_addr(&buf[73..77]) {
+        state.log_host.addr = addr;
+    }
+    if let Some(addr) = get_addr(&buf[77..81]) {
+        state.osc_host.addr = addr;
+    }
+    if let Some(port) = get_port(&buf[81..83]) {
+        state.osc_host.port = port;
+    }
+    if let Some(pin) = get_str(&buf[83..92]) {
+        state.web_pin = StaticString::new(pin);
+    }
+    for (i, address) in state.osc_addresses.iter_mut().enumerate() {
+        if let Some(s) = get_str(&buf[OSC_OFFSET + i * 33..OSC_OFFSET + (i + 1) * 33]) {
+            *address = StaticString::new(s);
+        }
+    }
+    if let Some(level) = Level::ALL.get(buf[488] as usize) {
+        logger::set_level(*level);
+    }
+    if (buf[489] as usize) < MAX_CORES {
+        network2::set_master_core(buf[489] as usize);
+    }
+    if buf[490] <= 1 {
+        network2::set_mirror_control(buf[490] == 1);
+    }
+}
+
+/// Writes `s` length first into `field`, cut to fit.
+fn put_str(field: &mut [u8], s: &str) {
+    let s = &s.as_bytes()[..s.len().min(field.len() - 1)];
+    field[0] = s.len() as u8;
+    field[1..1 + s.len()].copy_from_slice(s);
+}
+
+fn get_str(field: &[u8]) -> Option<&str> {
+    field
+        .get(1..1 + field[0] as usize)
+        .and_then(|s| core::str::from_utf8(s).ok())
+}
+
+// The broadcast address is never a valid setting, so an erased field reads as missing
+fn get_addr(field: &[u8]) -> Option<[u8; 4]> {
+    let addr: [u8; 4] = field.try_into().ok()?;
+    (addr != [0xFF; 4]).then_some(addr)
+}
+
+fn get_port(field: &[u8]) -> Option<u16> {
+    let port = u16::from_le_bytes(field.try_into().ok()?);
+    (port != 0xFFFF).then_some(port)
+}
+
+/// Loads saved settings, keeping the defaults for anything missing.
+pub fn load(flash: &mut SettingsFlash, state: &mut SystemState) {
+    let mut buf = [0u8; RECORD_LEN];
+    if flash.blocking_read(SETTINGS_OFFSET, &mut buf).is_err() || buf[..4] != SETTINGS_MAGIC {
+        logger::log(Level::Info, "settings", format_args!("no saved settings"));
+        return;
+    }
+    apply(&buf, state);
+}
+
+/// Replaces the Lock screen logo, or goes back to the built-in one with `None`.
+pub fn store_logo(logo: Option<[u8; LOGO_LEN]>) -> bool {
+    LOGO_UPLOAD.try_send(logo).is_ok()
+}
+
+/// Loads an uploaded logo, if there is one.
+pub fn load_logo(flash: &mut SettingsFlash) {
+    let mut magic = [0u8; 4];
+    if flash.blocking_read(LOGO_OFFSET, &mut magic).is_err() || magic != LOGO_MAGIC {
+        return;
+    }
+    let mut logo = [0u8; LOGO_LEN];
+    if flash.blocking_read(LOGO_OFFSET + 4, &mut logo).is_ok() {
+        graphics::set_custom_logo(Some(logo));
+    }
+}
+
+fn write_sector(flash: &mut SettingsFlash, offset: u32, parts: &[&[u8]]) -> Result<(), Error> {
+    flash.blocking_erase(offset, offset + ERASE_SIZE as u32)?;
+    let mut at = offset;
+    for part in parts {
+        flash.blocking_write(at, part)?;
+        at += part.len() as u32;
+    }
+    Ok(())
+}
+
+fn save_settings(flash: &mut SettingsFlash, buf: &[u8; RECORD_LEN]) {
+    let mut current = [0u8; RECORD_LEN];
+    if flash.blocking_read(SETTINGS_OFFSET, &mut current).is_ok() && current == *buf {
+        return;
+    }
+    match write_sector(flash, SETTINGS_OFFSET, &[buf]) {
+        Ok(()) => logger::log(Level::Info, "settings", format_args!("saved")),
+        Err(err) => {
+            logger::log(
+                Level::Error,
+                "settings",
+                format_args!("save failed: {:?}", err),
+            );
+            ui::notify(Priority::Warning, "Settings not saved");
+        }
+    }
+}
+
+async fn save_logo(flash: &SharedFlash, logo: Option<[u8; LOGO_LEN]>) {
+    let res = flash.lock(|flash| {
+        let mut flash = flash.borrow_mut();
+        match &logo {
+            Some(data) => write_sector(&mut flash, LOGO_OFFSET, &[&LOGO_MAGIC, data]),
+            // An erased sector has no magic, which is all it takes to fall back
+            None => flash.blocking_erase(LOGO_OFFSET, LOGO_OFFSET + ERASE_SIZE as u32),
+        }
+    });
+    match res {
+        Ok(()) => {
+            graphics::set_custom_logo(logo);
+            logger::log(Level::Info, "settings", format_args!("logo saved"));
+            ui::notify(Priority::Info, "Logo updated");
+            ACTION_UPSTREAM.send(Action::ForceRedraw).await;
+        }
+        Err(err) => {
+            logger::log(
+                Level::Error,
+                "settings",
+                format_args!("logo save failed: {:?}", err),
+            );
+            ui::notify(Priority::Warning, "Logo not saved");
+        }
+    }
+}
+
+#[embassy_executor::task]
+pub async fn settings_task(flash: &'static SharedFlash) {
+    loop {
+        match select(SAVE.wait(), LOGO_UPLOAD.receive()).await {
+            Either::First(()) => {
+                // Restart the delay on every further change
+                while SAVE.wait().with_timeout(SAVE_DELAY).await.is_ok() {}
+                let buf = encode(&*STATE.lock().await);
+                flash.lock(|flash| save_settings(&mut flash.borrow_mut(), &buf));
+            }
+            Either::Second(logo) => save_logo(flash, logo).await,
+        }
+    }
+}
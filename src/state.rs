@@ -1,8 +1,125 @@
+use crate::eventlog::EventLog;
+use crate::events::ButtonId;
+use crate::identity::MacAddress;
+use crate::logger;
+use crate::supervisor::ResetReport;
+use crate::timers::Timers;
 use common::{
     beat::Beat,
     cue::CueMetadata,
     mem::{network::IpAddress, str::StaticString},
 };
+use embassy_time::Instant;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LinkStatus {
+    #[default]
+    NoLink,
+    DhcpPending,
+    Subscribed,
+    Stale,
+    /// The core told us it was shutting down and no backup could take over.
+    Offline,
+}
+
+impl LinkStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LinkStatus::NoLink => "No link",
+            LinkStatus::DhcpPending => "DHCP",
+            LinkStatus::Subscribed => "Subscribed",
+            LinkStatus::Stale => "Stale",
+            LinkStatus::Offline => "Core offline",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NetMode {
+    #[default]
+    Dhcp,
+    Static,
+}
+
+impl NetMode {
+    pub const ALL: [NetMode; 2] = [NetMode::Dhcp, NetMode::Static];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            NetMode::Dhcp => "DHCP",
+            NetMode::Static => "Static",
+        }
+    }
+}
+
+pub const DEFAULT_DEVICE_NAME: &str = "ClicKS Hardware Controller";
+
+/// Slot 0 is the primary core, the rest are backups in the order we fail over to them.
+pub const MAX_CORES: usize = 4;
+
+/// How sending the last control request to a core went. The protocol has no ack, so this
+/// only tells whether it got onto the wire, not whether the core acted on it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SendStatus {
+    #[default]
+    Idle,
+    Sent,
+    Failed,
+}
+
+impl SendStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SendStatus::Idle => "-",
+            SendStatus::Sent => "sent",
+            SendStatus::Failed => "send failed",
+        }
+    }
+}
+
+/// A configured core and what we have heard from it. Unused while the address is 0.0.0.0.
+#[derive(Clone, Copy, Default)]
+pub struct Core {
+    pub ip: IpAddress,
+    pub last_heartbeat: Option<Instant>,
+    /// When our last ping went out, until the heartbeat that answers it.
+    pub ping_sent: Option<Instant>,
+    /// Ping to heartbeat, from the last ping answered.
+    pub rtt_ms: Option<u32>,
+    pub last_send: SendStatus,
+    /// Said it was shutting down, until we hear from it again.
+    pub offline: bool,
+}
+
+impl Core {
+    const fn unused(port: u16) -> Self {
+        Self {
+            ip: IpAddress {
+                port,
+                addr: [0, 0, 0, 0],
+            },
+            last_heartbeat: None,
+            ping_sent: None,
+            rtt_ms: None,
+            last_send: SendStatus::Idle,
+            offline: false,
+        }
+    }
+
+    pub fn configured(&self) -> bool {
+        self.ip.addr != [0, 0, 0, 0]
+    }
+
+    /// Short name of the role of the core in `slot`.
+    pub fn role(slot: usize) -> &'static str {
+        match slot {
+            0 => "Pri",
+            1 => "Bk1",
+            2 => "Bk2",
+            _ => "Bk3",
+        }
+    }
+}
 
 #[derive(Clone, Copy, Default)]
 pub struct TrackedValue<T> {
@@ -47,35 +164,120 @@ impl<T: Clone> TrackedValue<T> {
     }
 }
 
+/// Half a second to the beat until the core says otherwise.
+const BLANK_BEAT: Beat = Beat {
+    length: 500000,
+    count: 1,
+    bar_number: 0,
+};
+
 #[derive(Clone, Default)]
 pub struct SystemState {
     pub cue_metadata: CueMetadata,
     pub cue_idx: u16,
     pub beat: Beat,
     pub mark_label: StaticString<8>,
-    pub core_ip: IpAddress,
+    pub cores: [Core; MAX_CORES],
+    /// Slot of the core we follow and send control requests to.
+    pub active_core: usize,
     pub self_ip: IpAddress,
+    pub log_host: IpAddress,
+    pub net_mode: NetMode,
+    // Address used in static mode, the port is unused
+    pub static_ip: IpAddress,
+    /// Netmask of the static address, as a prefix length.
+    pub static_prefix: u8,
+    // Default route in static mode, none while the address is 0.0.0.0
+    pub gateway: IpAddress,
+    pub device_name: StaticString<32>,
+    /// Of the flash chip, what the MAC address is derived from. None if it couldn't be read,
+    /// the address is random then.
+    pub unique_id: Option<u64>,
+    pub mac: MacAddress,
+    /// Asked for before the web page changes anything. Settings can't be changed over
+    /// HTTP until one is set.
+    pub web_pin: StaticString<8>,
+    // Where button presses are sent as OSC, nowhere while the address is 0.0.0.0
+    pub osc_host: IpAddress,
+    /// OSC address sent for each button, by `ButtonId`. Empty means `/clicks/button/<name>`.
+    pub osc_addresses: [StaticString<32>; ButtonId::ALL.len()],
+    pub link: LinkStatus,
+    pub timers: Timers,
+    pub events: EventLog,
+    pub reset_report: Option<ResetReport>,
 }
 
 impl SystemState {
     pub const fn new() -> Self {
         Self {
-            beat: Beat {
-                length: 500000,
-                count: 1,
-                bar_number: 0,
-            },
+            beat: BLANK_BEAT,
             cue_metadata: CueMetadata::const_default(),
             cue_idx: 0,
             mark_label: StaticString::empty(),
-            core_ip: IpAddress {
-                port: 8081,
-                addr: [192, 168, 1, 135],
-            },
+            cores: [
+                Core {
+                    ip: IpAddress {
+                        port: 8081,
+                        addr: [192, 168, 1, 135],
+                    },
+                    ..Core::unused(8081)
+                },
+                Core::unused(8081),
+                Core::unused(8081),
+                Core::unused(8081),
+            ],
+            active_core: 0,
             self_ip: IpAddress {
                 port: 0,
                 addr: [0, 0, 0, 0],
             },
+            log_host: IpAddress {
+                port: logger::SYSLOG_PORT,
+                addr: [0, 0, 0, 0],
+            },
+            net_mode: NetMode::Dhcp,
+            static_ip: IpAddress {
+                port: 0,
+                addr: [192, 168, 1, 200],
+            },
+            static_prefix: 24,
+            gateway: IpAddress {
+                port: 0,
+                addr: [0, 0, 0, 0],
+            },
+            device_name: StaticString::empty(),
+            unique_id: None,
+            mac: MacAddress([0x02, 0, 0, 0, 0, 0]),
+            web_pin: StaticString::empty(),
+            osc_host: IpAddress {
+                port: 53000,
+                addr: [0, 0, 0, 0],
+            },
+            osc_addresses: [const { StaticString::empty() }; ButtonId::ALL.len()],
+            link: LinkStatus::NoLink,
+            timers: Timers::new(),
+            events: EventLog::new(),
+            reset_report: None,
+        }
+    }
+
+    /// Forgets what the core last told us about the show, so none of it reads as current.
+    pub fn clear_core_data(&mut self) {
+        self.cue_metadata = CueMetadata::const_default();
+        self.cue_idx = 0;
+        self.beat = BLANK_BEAT;
+        self.mark_label = StaticString::empty();
+    }
+
+    pub fn core(&self) -> &Core {
+        &self.cores[self.active_core]
+    }
+
+    pub fn device_name(&self) -> &str {
+        if self.device_name.len() == 0 {
+            DEFAULT_DEVICE_NAME
+        } else {
+            self.device_name.str()
         }
     }
 }
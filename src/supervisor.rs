@@ -0,0 +1,225 @@
+use crate::logger::{self, Level};
+use common::mem::str::StaticString;
+use core::fmt::Write;
+use core::mem::MaybeUninit;
+use core::panic::PanicInfo;
+use embassy_rp::watchdog::{ResetReason, Watchdog};
+use embassy_time::{Duration, Instant, Timer};
+use portable_atomic::{AtomicU32, Ordering};
+
+const WATCHDOG_TIMEOUT: Duration = Duration::from_millis(1000);
+const CHECK_INTERVAL: Duration = Duration::from_millis(250);
+const STALL_TIMEOUT_MS: u32 = 5000;
+
+const RECORD_MAGIC: u32 = 0xC1C5_DEAD;
+const RECORD_MSG_LEN: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskId {
+    Buttons,
+    Ui,
+    Network,
+}
+
+impl TaskId {
+    pub const ALL: [TaskId; 3] = [TaskId::Buttons, TaskId::Ui, TaskId::Network];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TaskId::Buttons => "Buttons",
+            TaskId::Ui => "UI",
+            TaskId::Network => "Network",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetCause {
+    PowerOn,
+    Forced,
+    Watchdog,
+    Stall(TaskId),
+    Panic,
+}
+
+#[derive(Clone, Copy)]
+pub struct ResetReport {
+    pub cause: ResetCause,
+    pub message: StaticString<64>,
+    pub reported: bool,
+}
+
+impl ResetReport {
+    pub fn is_crash(&self) -> bool {
+        !matches!(self.cause, ResetCause::PowerOn | ResetCause::Forced)
+    }
+
+    pub fn short(&self) -> StaticString<32> {
+        match self.cause {
+            ResetCause::PowerOn => StaticString::new("Power on"),
+            ResetCause::Forced => StaticString::new("Reboot"),
+            ResetCause::Watchdog => StaticString::new("Watchdog"),
+            ResetCause::Panic => StaticString::new("Panic"),
+            ResetCause::Stall(task) => {
+                let mut buf = [0u8; 32];
+                let s = format_no_std::show(&mut buf, format_args!("Stall {}", task.label()))
+                    .unwrap_or_default();
+                StaticString::new(s)
+            }
+        }
+    }
+}
+
+/// Survives a watchdog reset, is only trusted when `magic` matches. Kept in its own RAM
+/// region, the bootloader runs on every reset and its stack would run over `.uninit`.
+#[repr(C)]
+struct CrashRecord {
+    magic: u32,
+    cause: u8,
+    task: u8,
+    len: u8,
+    msg: [u8; RECORD_MSG_LEN],
+}
+
+#[unsafe(link_section = ".crash.CRASH_RECORD")]
+static mut CRASH_RECORD: MaybeUninit<CrashRecord> = MaybeUninit::uninit();
+
+const CAUSE_PANIC: u8 = 1;
+const CAUSE_STALL: u8 = 2;
+
+fn write_record(cause: u8, task: u8, msg: &[u8]) {
+    let len = msg.len().min(RECORD_MSG_LEN);
+    let mut record = CrashRecord {
+        magic: RECORD_MAGIC,
+        cause,
+        task,
+        len: len as u8,
+        msg: [0; RECORD_MSG_LEN],
+    };
+    record.msg[..len].copy_from_slice(&msg[..len]);
+    unsafe { core::ptr::write_volatile(&raw mut CRASH_RECORD, MaybeUninit::new(record)) };
+}
+
+fn take_record() -> Option<CrashRecord> {
+    let record = unsafe { core::ptr::read_volatile(&raw const CRASH_RECORD).assume_init() };
+    unsafe {
+        core::ptr::write_volatile(
+            &raw mut CRASH_RECORD,
+            MaybeUninit::new(CrashRecord {
+                magic: 0,
+                cause: 0,
+                task: 0,
+                len: 0,
+                msg: [0; RECORD_MSG_LEN],
+            }),
+        )
+    };
+    if record.magic == RECORD_MAGIC {
+        Some(record)
+    } else {
+        None
+    }
+}
+
+/// Works out why we came out of reset, combining the watchdog reason with whatever the
+/// previous run left in the crash record.
+pub fn take_reset_report(reason: Option<ResetReason>) -> ResetReport {
+    let record = take_record();
+    let message = record
+        .as_ref()
+        .and_then(|r| core::str::from_utf8(&r.msg[..(r.len as usize).min(RECORD_MSG_LEN)]).ok())
+        .map(StaticString::new)
+        .unwrap_or(StaticString::empty());
+    let cause = match (&record, reason) {
+        (Some(r), _) if r.cause == CAUSE_PANIC => ResetCause::Panic,
+        (Some(r), _) if r.cause == CAUSE_STALL => {
+            ResetCause::Stall(*TaskId::ALL.get(r.task as usize).unwrap_or(&TaskId::Network))
+        }
+        (_, Some(ResetReason::TimedOut)) => ResetCause::Watchdog,
+        (_, Some(ResetReason::Forced)) => ResetCause::Forced,
+        _ => ResetCause::PowerOn,
+    };
+    ResetReport {
+        cause,
+        message,
+        reported: false,
+    }
+}
+
+static HEARTBEATS: [AtomicU32; TaskId::ALL.len()] =
+    [const { AtomicU32::new(0) }; TaskId::ALL.len()];
+
+fn now_ms() -> u32 {
+    Instant::now().as_millis() as u32
+}
+
+/// Called by supervised tasks from their main loop to prove they are still making progress.
+pub fn beat(task: TaskId) {
+    HEARTBEATS[task as usize].store(now_ms(), Ordering::Relaxed);
+}
+
+#[embassy_executor::task]
+pub async fn supervisor_task(mut wd: Watchdog) {
+    for task in TaskId::ALL {
+        beat(task);
+    }
+    wd.start(WATCHDOG_TIMEOUT);
+
+    loop {
+        Timer::after(CHECK_INTERVAL).await;
+        let now = now_ms();
+        let stalled = TaskId::ALL.iter().find(|task| {
+            now.wrapping_sub(HEARTBEATS[**task as usize].load(Ordering::Relaxed)) > STALL_TIMEOUT_MS
+        });
+        if let Some(task) = stalled {
+            logger::log(
+                Level::Error,
+                "supervisor",
+                format_args!("{} task stalled", task.label()),
+            );
+            let mut buf = [0u8; RECORD_MSG_LEN];
+            let msg = format_no_std::show(&mut buf, format_args!("{} task stalled", task.label()))
+                .unwrap_or_default();
+            write_record(CAUSE_STALL, *task as u8, msg.as_bytes());
+            wd.trigger_reset();
+        }
+        wd.feed();
+    }
+}
+
+struct RecordWriter {
+    buf: [u8; RECORD_MSG_LEN],
+    len: usize,
+}
+
+impl Write for RecordWriter {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let n = s.len().min(RECORD_MSG_LEN - self.len);
+        // Don't split a multi-byte character at the end of the buffer
+        let n = (0..=n).rev().find(|i| s.is_char_boundary(*i)).unwrap_or(0);
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+    defmt::error!("{}", defmt::Display2Format(info));
+
+    let mut w = RecordWriter {
+        buf: [0; RECORD_MSG_LEN],
+        len: 0,
+    };
+    if let Some(loc) = info.location() {
+        let file = loc.file().rsplit('/').next().unwrap_or_default();
+        let _ = write!(w, "{}:{} ", file, loc.line());
+    }
+    let _ = write!(w, "{}", info.message());
+    write_record(CAUSE_PANIC, 0, &w.buf[..w.len]);
+
+    // Restart straight away rather than wait for the watchdog, which isn't running yet if
+    // we panicked during startup. RAM survives this, and the record with it.
+    cortex_m::peripheral::SCB::sys_reset()
+}
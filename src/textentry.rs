@@ -1,6 +1,8 @@
 use crate::{
-    events::{Action, Mode},
-    ACTION_SRC, ACTION_UPSTREAM, MODE_SIGNAL, STATE, UX_CH,
+    events::{Action, Mode, Priority},
+    logger, settings,
+    timers::{self, TimerId},
+    ui, ACTION_SRC, ACTION_UPSTREAM, MODE_SIGNAL, STATE, UX_CH,
 };
 use common::mem::{network::IpAddress, str::StaticString};
 
@@ -8,7 +10,12 @@ use common::mem::{network::IpAddress, str::StaticString};
 pub enum TextEntryContext {
     Unknown,
     CoreIPv4,
+    BackupIPv4,
     CorePort,
+    LogHostIPv4,
+    CountdownA,
+    CountdownB,
+    WebPin,
 }
 
 #[embassy_executor::task]
@@ -51,16 +58,55 @@ pub async fn text_entry_task() {
                     let mut system = STATE.lock().await;
                     match edit_context {
                         TextEntryContext::CorePort => {
-                            system.core_ip.port = buffer.str().parse().unwrap_or_default();
+                            // All cores of a show listen on the same port
+                            let port = buffer.str().parse().unwrap_or_default();
+                            for core in system.cores.iter_mut() {
+                                core.ip.port = port;
+                            }
                         }
-                        TextEntryContext::CoreIPv4 => {
-                            system.core_ip =
-                                IpAddress::from_str_and_port(buffer.str(), system.core_ip.port)
-                                    .unwrap_or_default();
+                        TextEntryContext::CoreIPv4 | TextEntryContext::BackupIPv4 => {
+                            let slot = (edit_context == TextEntryContext::BackupIPv4) as usize;
+                            let port = system.cores[slot].ip.port;
+                            system.cores[slot].ip =
+                                IpAddress::from_str_and_port(buffer.str(), port).unwrap_or(
+                                    IpAddress {
+                                        port,
+                                        addr: [0, 0, 0, 0],
+                                    },
+                                );
+                        }
+                        TextEntryContext::LogHostIPv4 => {
+                            system.log_host =
+                                IpAddress::from_str_and_port(buffer.str(), logger::SYSLOG_PORT)
+                                    .unwrap_or(IpAddress {
+                                        port: logger::SYSLOG_PORT,
+                                        addr: [0, 0, 0, 0],
+                                    });
+                        }
+                        TextEntryContext::CountdownA | TextEntryContext::CountdownB => {
+                            let id = if edit_context == TextEntryContext::CountdownA {
+                                TimerId::CountdownA
+                            } else {
+                                TimerId::CountdownB
+                            };
+                            if let Some(length) = timers::parse_length(buffer.str()) {
+                                system.timers.get_mut(id).set_length(length);
+                            }
+                        }
+                        TextEntryContext::WebPin => {
+                            // Empty turns changes over HTTP off again
+                            system.web_pin = StaticString::new(buffer.str());
                         }
                         _ => {}
                     }
+                    drop(system);
+                    settings::save();
+                    if edit_context == TextEntryContext::LogHostIPv4 {
+                        // The log host is only read when connecting
+                        tx.send(Action::ReloadConnection).await;
+                    }
                     tx.try_send(Action::ModeChange(Mode::Menu));
+                    ui::notify(Priority::Info, "Setting saved");
                     break;
                 }
                 _ => {}
@@ -0,0 +1,236 @@
+use crate::diagnostics::{self, ChannelId};
+use crate::events::{Action, Priority};
+use crate::logger::{self, Level};
+use crate::{ui, LED_CH, STATE, TIMER_CH};
+use common::mem::str::StaticString;
+use embassy_futures::select::{select, Either};
+use embassy_time::{Duration, Instant, Ticker};
+
+const CHECK_INTERVAL: Duration = Duration::from_millis(250);
+/// How long the LEDs flash for an expired countdown nobody acknowledges.
+const ALARM_DURATION: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerId {
+    Stopwatch,
+    CountdownA,
+    CountdownB,
+}
+
+impl TimerId {
+    pub const ALL: [TimerId; 3] = [TimerId::Stopwatch, TimerId::CountdownA, TimerId::CountdownB];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimerId::Stopwatch => "Stopwatch",
+            TimerId::CountdownA => "Count A",
+            TimerId::CountdownB => "Count B",
+        }
+    }
+}
+
+/// Runs on timestamps rather than ticks, so it stays right no matter who looks at it when.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timer {
+    countdown: Option<Duration>,
+    started: Option<Instant>,
+    banked: Duration,
+    pub expired: bool,
+}
+
+impl Timer {
+    const fn new(countdown: Option<Duration>) -> Self {
+        Self {
+            countdown,
+            started: None,
+            banked: Duration::from_ticks(0),
+            expired: false,
+        }
+    }
+
+    pub fn running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        self.banked + self.started.map(|s| now - s).unwrap_or_default()
+    }
+
+    /// Time left on a countdown, or time elapsed on a stopwatch.
+    pub fn shown(&self, now: Instant) -> Duration {
+        match self.countdown {
+            Some(length) => length.checked_sub(self.elapsed(now)).unwrap_or_default(),
+            None => self.elapsed(now),
+        }
+    }
+
+    pub fn length(&self) -> Option<Duration> {
+        self.countdown
+    }
+
+    pub fn set_length(&mut self, length: Duration) {
+        if self.countdown.is_some() {
+            self.countdown = Some(length);
+            self.reset();
+        }
+    }
+
+    pub fn start_stop(&mut self, now: Instant) {
+        match self.started.take() {
+            Some(s) => self.banked += now - s,
+            None if self.expired => {}
+            None => self.started = Some(now),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.started = None;
+        self.banked = Duration::from_ticks(0);
+        self.expired = false;
+    }
+
+    /// Returns true the first time a running countdown reaches zero.
+    fn check_expiry(&mut self, now: Instant) -> bool {
+        let Some(length) = self.countdown else {
+            return false;
+        };
+        if self.running() && self.elapsed(now) >= length {
+            self.banked = length;
+            self.started = None;
+            self.expired = true;
+            return true;
+        }
+        false
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timers {
+    timers: [Timer; TimerId::ALL.len()],
+    /// The one shown by the main screen timer widget, whichever was last started.
+    pub featured: TimerId,
+}
+
+impl Default for Timers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timers {
+    pub const fn new() -> Self {
+        Self {
+            timers: [
+                Timer::new(None),
+                Timer::new(Some(Duration::from_secs(15 * 60))),
+                Timer::new(Some(Duration::from_secs(5 * 60))),
+            ],
+            featured: TimerId::CountdownA,
+        }
+    }
+
+    pub fn get(&self, id: TimerId) -> &Timer {
+        &self.timers[id as usize]
+    }
+
+    pub fn get_mut(&mut self, id: TimerId) -> &mut Timer {
+        &mut self.timers[id as usize]
+    }
+}
+
+/// Formats as MM:SS, or H:MM:SS from an hour up.
+pub fn format(d: Duration) -> StaticString<16> {
+    let secs = d.as_secs();
+    let mut buf = [0u8; 16];
+    let s = if secs >= 3600 {
+        format_no_std::show(
+            &mut buf,
+            format_args!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60),
+        )
+    } else {
+        format_no_std::show(&mut buf, format_args!("{:02}:{:02}", secs / 60, secs % 60))
+    }
+    .unwrap_or_default();
+    StaticString::new(s)
+}
+
+/// Parses a countdown length typed as minutes, optionally followed by `.` and seconds.
+pub fn parse_length(s: &str) -> Option<Duration> {
+    let (min, sec) = s.trim().split_once('.').unwrap_or((s.trim(), "0"));
+    let min: u64 = if min.is_empty() { 0 } else { min.parse().ok()? };
+    let sec: u64 = sec.parse().ok()?;
+    if sec >= 60 || (min == 0 && sec == 0) {
+        return None;
+    }
+    Some(Duration::from_secs(min * 60 + sec))
+}
+
+#[embassy_executor::task]
+pub async fn timer_task() {
+    let mut check = Ticker::every(CHECK_INTERVAL);
+    let mut alarm_until: Option<Instant> = None;
+
+    loop {
+        match select(TIMER_CH.receive(), check.next()).await {
+            Either::First(action) => {
+                let now = Instant::now();
+                let mut state = STATE.lock().await;
+                let timers = &mut state.timers;
+                let action = match action {
+                    Action::FeaturedTimerStartStop => Action::TimerStartStop(timers.featured),
+                    Action::FeaturedTimerReset => Action::TimerReset(timers.featured),
+                    other => other,
+                };
+                let acted = match action {
+                    Action::TimerStartStop(id) => {
+                        if timers.get(id).expired {
+                            timers.get_mut(id).reset();
+                        } else {
+                            timers.get_mut(id).start_stop(now);
+                        }
+                        timers.featured = id;
+                        true
+                    }
+                    Action::TimerReset(id) => {
+                        timers.get_mut(id).reset();
+                        true
+                    }
+                    _ => false,
+                };
+                drop(state);
+                // Any timer button silences the alarm
+                if acted && alarm_until.take().is_some() {
+                    LED_CH.send(Action::TimerAlarm(false)).await;
+                }
+            }
+            Either::Second(_) => {
+                let now = Instant::now();
+                let mut state = STATE.lock().await;
+                for id in TimerId::ALL {
+                    if state.timers.get_mut(id).check_expiry(now) {
+                        let mut buf = [0u8; 32];
+                        let msg =
+                            format_no_std::show(&mut buf, format_args!("{} done", id.label()))
+                                .unwrap_or_default();
+                        logger::log(Level::Info, "timers", format_args!("{}", msg));
+                        ui::notify(Priority::Critical, msg);
+                        if alarm_until.is_none() {
+                            diagnostics::try_forward(
+                                &LED_CH,
+                                ChannelId::Led,
+                                Action::TimerAlarm(true),
+                            );
+                        }
+                        alarm_until = Some(now + ALARM_DURATION);
+                    }
+                }
+                drop(state);
+
+                if alarm_until.is_some_and(|until| now >= until) {
+                    alarm_until = None;
+                    LED_CH.send(Action::TimerAlarm(false)).await;
+                }
+            }
+        }
+    }
+}
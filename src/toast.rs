@@ -0,0 +1,104 @@
+use crate::events::{Notification, Priority};
+use embassy_time::{Duration, Instant};
+
+const QUEUE_LEN: usize = 4;
+
+fn display_time(priority: Priority) -> Duration {
+    match priority {
+        Priority::Info => Duration::from_secs(2),
+        Priority::Warning => Duration::from_secs(3),
+        Priority::Critical => Duration::from_secs(5),
+    }
+}
+
+/// Notification currently in the banner, plus the ones waiting for it.
+pub struct Toasts {
+    current: Option<(Notification, Instant)>,
+    queue: [Option<Notification>; QUEUE_LEN],
+}
+
+impl Toasts {
+    pub const fn new() -> Self {
+        Self {
+            current: None,
+            queue: [None; QUEUE_LEN],
+        }
+    }
+
+    pub fn current(&self) -> Option<Notification> {
+        self.current.map(|(n, _)| n)
+    }
+
+    /// Returns true if the banner content changed.
+    pub fn push(&mut self, n: Notification) -> bool {
+        match self.current {
+            Some((cur, _)) if n.priority <= cur.priority => {
+                self.enqueue(n);
+                false
+            }
+            Some((cur, _)) => {
+                // Preempted notifications get shown again afterwards
+                self.enqueue(cur);
+                self.current = Some((n, Instant::now()));
+                true
+            }
+            None => {
+                self.current = Some((n, Instant::now()));
+                true
+            }
+        }
+    }
+
+    /// Returns true if the banner content changed.
+    pub fn expire(&mut self, now: Instant) -> bool {
+        match self.current {
+            Some((cur, shown)) if now - shown >= display_time(cur.priority) => {
+                self.current = self.dequeue().map(|n| (n, now));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn enqueue(&mut self, n: Notification) {
+        if let Some(slot) = self.queue.iter_mut().find(|s| s.is_none()) {
+            *slot = Some(n);
+            return;
+        }
+        // Full, make room by dropping the oldest of the least important ones
+        let lowest = self
+            .queue
+            .iter()
+            .flatten()
+            .map(|q| q.priority)
+            .min()
+            .unwrap_or(Priority::Info);
+        if n.priority < lowest {
+            return;
+        }
+        if let Some(idx) = self
+            .queue
+            .iter()
+            .position(|q| q.map(|q| q.priority) == Some(lowest))
+        {
+            self.remove(idx);
+            self.queue[QUEUE_LEN - 1] = Some(n);
+        }
+    }
+
+    fn dequeue(&mut self) -> Option<Notification> {
+        let highest = self.queue.iter().flatten().map(|q| q.priority).max()?;
+        let idx = self
+            .queue
+            .iter()
+            .position(|q| q.map(|q| q.priority) == Some(highest))?;
+        let n = self.queue[idx];
+        self.remove(idx);
+        n
+    }
+
+    fn remove(&mut self, idx: usize) {
+        self.queue[idx..].rotate_left(1);
+        self.queue[QUEUE_LEN - 1] = None;
+    }
+}
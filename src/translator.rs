@@ -1,10 +1,37 @@
 use crate::events::{Action, ButtonId, Mode};
-use crate::{menu, ACTION_SRC, ACTION_UPSTREAM, BUTTON_CH, MODE_SIGNAL, STATE};
+use crate::osc;
+use crate::{menu, ui, ACTION_SRC, ACTION_UPSTREAM, BUTTON_CH, MODE_SIGNAL, STATE};
 use common::event::JumpModeChange;
 use common::protocol::request::{ControlAction, Request};
 use cortex_m::register::control::Control;
 use embassy_executor::task;
 use embassy_futures::select::{select, Either};
+use portable_atomic::{AtomicBool, Ordering};
+
+static SWAP_BUTTONS: AtomicBool = AtomicBool::new(false);
+
+/// Whether Next/Previous and Tempo+/- trade places, for units mounted upside down.
+pub fn swap_buttons() -> bool {
+    SWAP_BUTTONS.load(Ordering::Relaxed)
+}
+
+pub fn set_swap_buttons(swap: bool) {
+    SWAP_BUTTONS.store(swap, Ordering::Relaxed);
+}
+
+fn remap(mode: Mode, id: ButtonId) -> ButtonId {
+    // Text entry goes by the legends printed next to each button, which don't turn over
+    if !swap_buttons() || mode == Mode::TextEntry {
+        return id;
+    }
+    match id {
+        ButtonId::Next => ButtonId::Previous,
+        ButtonId::Previous => ButtonId::Next,
+        ButtonId::MetronomeTempoPlus => ButtonId::MetronomeTempoMinus,
+        ButtonId::MetronomeTempoMinus => ButtonId::MetronomeTempoPlus,
+        other => other,
+    }
+}
 
 #[task]
 pub async fn input_translator_task() {
@@ -23,7 +50,16 @@ pub async fn input_translator_task() {
                 }
 
                 if btn.pressed {
-                    action_lut(mode, shift, btn.id, playing)
+                    let waking = ui::idle();
+                    ui::wake();
+                    // The press that wakes the display only goes through if it runs the
+                    // show. Anything else waits until the operator can read the screen.
+                    if waking && !(mode == Mode::Main && is_transport(btn.id)) {
+                        None
+                    } else {
+                        osc::button_pressed(btn.id);
+                        action_lut(mode, shift, remap(mode, btn.id), playing)
+                    }
                 } else {
                     None
                 }
@@ -41,6 +77,14 @@ pub async fn input_translator_task() {
                     action_tx.send(Action::ModeChange(mode)).await;
                 }
                 Action::NewTransportData(data) => playing = data.running,
+                // Kept for whoever draws from STATE later: full redraws, the web page and
+                // the console
+                Action::NewCueData(idx, cue) => {
+                    let mut state = STATE.lock().await;
+                    state.cue_idx = idx;
+                    state.cue_metadata = cue;
+                }
+                Action::NewLabelData(label) => STATE.lock().await.mark_label = label,
                 _ => {}
             }
 
@@ -50,6 +94,13 @@ pub async fn input_translator_task() {
     }
 }
 
+fn is_transport(id: ButtonId) -> bool {
+    matches!(
+        id,
+        ButtonId::Start | ButtonId::Stop | ButtonId::Next | ButtonId::Previous
+    )
+}
+
 fn action_lut(mode: Mode, shift: bool, id: ButtonId, playing: bool) -> Option<Action> {
     match (mode, shift, id) {
         (Mode::Lock, _, ButtonId::Start) => None,
@@ -62,6 +113,8 @@ fn action_lut(mode: Mode, shift: bool, id: ButtonId, playing: bool) -> Option<Ac
         (Mode::Main, false, ButtonId::Previous) => Some(Action::RequestToCore(
             Request::ControlAction(ControlAction::LoadPreviousCue),
         )),
+        (Mode::Main, true, ButtonId::Next) => Some(Action::FeaturedTimerStartStop),
+        (Mode::Main, true, ButtonId::MetronomeStop) => Some(Action::FeaturedTimerReset),
         (Mode::Main, true, ButtonId::Previous) => Some(Action::RequestToCore(
             Request::ControlAction(ControlAction::TransportZero),
         )),
@@ -87,6 +140,24 @@ fn action_lut(mode: Mode, shift: bool, id: ButtonId, playing: bool) -> Option<Ac
         (Mode::Menu, false, ButtonId::Previous) => Some(Action::PreviousItem),
         (Mode::Menu, false, ButtonId::Menu) => Some(Action::SelectItem),
         (Mode::Menu, true, ButtonId::Menu) => Some(Action::ModeChange(Mode::Main)),
+        (Mode::Diagnostics, false, ButtonId::Next) => Some(Action::NextItem),
+        (Mode::Diagnostics, false, ButtonId::Previous) => Some(Action::PreviousItem),
+        (Mode::Diagnostics, false, ButtonId::Menu) => Some(Action::ModeChange(Mode::Menu)),
+        (Mode::Diagnostics, true, ButtonId::Menu) => Some(Action::ModeChange(Mode::Main)),
+        (Mode::Timers, false, ButtonId::Next) => Some(Action::NextItem),
+        (Mode::Timers, false, ButtonId::Previous) => Some(Action::PreviousItem),
+        (Mode::Timers, _, ButtonId::Start) => Some(Action::SelectItem),
+        (Mode::Timers, _, ButtonId::Stop) => Some(Action::ResetItem),
+        (Mode::Timers, false, ButtonId::Menu) => Some(Action::ModeChange(Mode::Menu)),
+        (Mode::Timers, true, ButtonId::Menu) => Some(Action::ModeChange(Mode::Main)),
+        (Mode::Events, false, ButtonId::Next) => Some(Action::NextItem),
+        (Mode::Events, false, ButtonId::Previous) => Some(Action::PreviousItem),
+        (Mode::Events, false, ButtonId::Menu) => Some(Action::ModeChange(Mode::Menu)),
+        (Mode::Events, true, ButtonId::Menu) => Some(Action::ModeChange(Mode::Main)),
+        (Mode::About, false, ButtonId::Menu) => Some(Action::ModeChange(Mode::Menu)),
+        (Mode::About, true, ButtonId::Menu) => Some(Action::ModeChange(Mode::Main)),
+        (Mode::Network, false, ButtonId::Menu) => Some(Action::ModeChange(Mode::Menu)),
+        (Mode::Network, true, ButtonId::Menu) => Some(Action::ModeChange(Mode::Main)),
         (Mode::TextEntry, false, ButtonId::Menu) => Some(Action::Confirm),
         (Mode::TextEntry, true, ButtonId::Menu) => Some(Action::ModeChange(Mode::Menu)),
         (Mode::TextEntry, false, ButtonId::MetronomeStop) => Some(Action::Character(b'1')),
@@ -1,36 +1,121 @@
-use crate::events::{Action, Mode};
-use crate::graphics::GraphicsController;
-use crate::menu::{self};
-use crate::state::SystemState;
+use crate::diagnostics::{self, ChannelId};
+use crate::eventlog::EventLog;
+use crate::events::{Action, Mode, Notification, Priority};
+use crate::graphics::{self, GraphicsController, MarqueeSlot};
+use crate::layout::{self, Layout, Widget};
+use crate::logger::{self, Level};
+use crate::menu::{self, MenuPage};
+use crate::metronome;
+use crate::state::{Core, LinkStatus, SystemState};
+use crate::supervisor::{self, TaskId};
+use crate::timers::{self, TimerId, Timers};
+use crate::toast::Toasts;
 use crate::{ACTION_UPSTREAM, STATE, UI_CH};
 use common::beat::Beat;
 use common::cue::CueMetadata;
+use common::event::JumpModeChange;
 use common::mem::str::StaticString;
+use common::protocol::request::{ControlAction, Request};
 use embassy_executor::task;
-use embassy_time::Timer;
+use embassy_futures::select::{select3, Either3};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Ticker};
 use embedded_graphics::pixelcolor::BinaryColor;
 use embedded_graphics::prelude::{Point, Size};
+use portable_atomic::{AtomicBool, Ordering};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+struct TransportView {
+    playing: bool,
+    vlt: bool,
+    jump_armed: bool,
+    // Playhead within the current cue, summed from the lengths of the beats played so far.
+    // Counting down would need the cue's length, which none of the data we handle carries
+    elapsed_us: u64,
+    beat_len_us: u64,
+}
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 struct ViewState {
     mode: Mode,
+    menu_page: MenuPage,
     selected_index: usize,
+    page: usize,
     text: StaticString<32>,
     bpm: u16,
+    transport: TransportView,
+    beat_count: u8,
+    beats_per_bar: u8,
+    // Position of the Lock screen saver text, while idle
+    screensaver: Option<Point>,
+    shift_step: u8,
+}
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+const MARQUEE_INTERVAL: Duration = Duration::from_millis(50);
+const PIXEL_SHIFT_INTERVAL: Duration = Duration::from_secs(60);
+const SCREENSAVER_INTERVAL: Duration = Duration::from_secs(5);
+const PIXEL_SHIFT: [Point; 4] = [
+    Point::new(0, 0),
+    Point::new(1, 0),
+    Point::new(1, 1),
+    Point::new(0, 1),
+];
+
+static ACTIVITY: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+static IDLE: AtomicBool = AtomicBool::new(false);
+
+/// Marks user activity that the UI doesn't otherwise see, such as unmapped button presses.
+pub fn wake() {
+    ACTIVITY.signal(());
+}
+
+/// Whether the display is dimmed. A press then only wakes it up, rather than acting on
+/// something the operator can't read yet. Transport buttons act all the same.
+pub fn idle() -> bool {
+    IDLE.load(Ordering::Relaxed)
+}
+
+/// Actions that count as activity and wake the display from idle. Beats are left out, they
+/// keep coming for as long as the show plays.
+fn wakes(action: &Action) -> bool {
+    matches!(
+        action,
+        Action::ModeChange(_)
+            | Action::NewCueData(..)
+            | Action::NewTransportData(_)
+            | Action::NewLabelData(_)
+            | Action::Notify(_)
+    )
 }
 
 pub fn debug_now(s: &str) {
-    ACTION_UPSTREAM.try_send(Action::DebugMessage {
-        msg: StaticString::new(s),
-    });
+    diagnostics::try_forward(
+        &ACTION_UPSTREAM,
+        ChannelId::Upstream,
+        Action::DebugMessage {
+            msg: StaticString::new(s),
+        },
+    );
 }
 pub async fn debug(s: &str) {
+    logger::log(Level::Debug, "ui", format_args!("{}", s));
     ACTION_UPSTREAM
         .send(Action::DebugMessage {
             msg: StaticString::new(s),
         })
         .await;
-    Timer::after_secs(1).await;
+}
+pub fn notify(priority: Priority, s: &str) {
+    diagnostics::try_forward(
+        &ACTION_UPSTREAM,
+        ChannelId::Upstream,
+        Action::Notify(Notification {
+            priority,
+            msg: StaticString::new(s),
+        }),
+    );
 }
 
 #[task]
@@ -39,21 +124,122 @@ pub async fn ui_task(mut gc: GraphicsController) {
 
     let mut state = ViewState {
         mode: Mode::Lock,
+        menu_page: MenuPage::Root,
         selected_index: 0,
+        page: 0,
         text: StaticString::new("Unused text"),
         bpm: 120,
+        transport: TransportView::default(),
+        beat_count: 0,
+        beats_per_bar: metronome::BEATS_PER_BAR,
+        screensaver: None,
+        shift_step: 0,
     };
 
     let gcm = &mut gc;
     redraw_full(&state, gcm).await;
 
+    let mut refresh = Ticker::every(REFRESH_INTERVAL);
+    let mut scroll = Ticker::every(MARQUEE_INTERVAL);
+    let mut was_scrolling = false;
+    let mut toasts = Toasts::new();
+    let mut last_activity = Instant::now();
+    let mut last_shift = Instant::now();
+    let mut last_saver_move = Instant::now();
+    let mut idle = false;
+
     loop {
-        let action = rx.receive().await;
+        // The marquee ticker only runs while something scrolls, and starts over when it does
+        let scrolling = gcm.scrolling();
+        if scrolling && !was_scrolling {
+            scroll.reset();
+        }
+        was_scrolling = scrolling;
+        let marquee_tick = async {
+            if scrolling {
+                scroll.next().await
+            } else {
+                core::future::pending().await
+            }
+        };
+        let next = select3(rx.receive(), refresh.next(), marquee_tick).await;
+        supervisor::beat(TaskId::Ui);
+        let woken = match &next {
+            Either3::First(action) => wakes(action),
+            _ => false,
+        };
+        if ACTIVITY.try_take().is_some() || woken {
+            last_activity = Instant::now();
+            if idle {
+                idle = false;
+                IDLE.store(false, Ordering::Relaxed);
+                state.screensaver = None;
+                gcm.set_dimmed(false);
+                redraw_full(&state, gcm).await;
+            }
+        }
+        let action = match next {
+            Either3::First(action) => action,
+            Either3::Third(_) => {
+                if gcm.scroll_marquees() {
+                    gcm.commit().await;
+                }
+                continue;
+            }
+            Either3::Second(_) => {
+                let now = Instant::now();
+                if toasts.expire(now) {
+                    show_toast(gcm, &toasts).await;
+                }
+                let mut need_full = matches!(
+                    state.mode,
+                    Mode::Diagnostics | Mode::Network | Mode::Timers | Mode::Events
+                );
+                // Never while the show plays, the operator may need it at any moment
+                if !idle
+                    && !state.transport.playing
+                    && graphics::idle_timeout()
+                        .duration()
+                        .is_some_and(|t| now - last_activity >= t)
+                {
+                    idle = true;
+                    IDLE.store(true, Ordering::Relaxed);
+                    gcm.set_dimmed(true);
+                    need_full = true;
+                }
+                if idle
+                    && state.mode == Mode::Lock
+                    && (state.screensaver.is_none()
+                        || now - last_saver_move >= SCREENSAVER_INTERVAL)
+                {
+                    last_saver_move = now;
+                    state.screensaver = Some(screensaver_pos(now.as_ticks()));
+                    need_full = true;
+                }
+                if state.mode == Mode::Main && now - last_shift >= PIXEL_SHIFT_INTERVAL {
+                    last_shift = now;
+                    state.shift_step = state.shift_step.wrapping_add(1);
+                    need_full = true;
+                }
+                if need_full {
+                    redraw_full(&state, gcm).await;
+                } else {
+                    if state.mode == Mode::Main {
+                        let timers = STATE.lock().await.timers;
+                        draw_main_timer(gcm, layout::layout(), &timers);
+                    }
+                    // Idle dimming and contrast changes go out with a commit
+                    gcm.commit().await;
+                }
+                continue;
+            }
+        };
         let mut need_redraw = false;
 
         match (state.mode, action) {
             (Mode::Menu, Action::NextItem) => {
-                state.selected_index = state.selected_index.saturating_add(1);
+                state.selected_index =
+                    (state.selected_index + 1).min(menu::len(state.menu_page) - 1);
                 need_redraw = true;
             }
             (Mode::Menu, Action::PreviousItem) => {
@@ -62,14 +248,59 @@ pub async fn ui_task(mut gc: GraphicsController) {
             }
             (Mode::Menu, Action::SelectItem) => {
                 let app_state = STATE.lock().await;
-                if let Some(action) = (menu::get_item(state.selected_index).exec)(app_state.clone())
+                if let Some(action) = menu::get_item(state.menu_page, state.selected_index)
+                    .and_then(|item| (item.exec)(app_state.clone()))
                 {
                     drop(app_state);
                     ACTION_UPSTREAM.send(action).await;
                 }
             }
+            (Mode::Menu, Action::OpenMenu(page)) => {
+                state.menu_page = page;
+                state.selected_index = 0;
+                redraw_full(&state, gcm).await;
+            }
+            (Mode::Timers, Action::NextItem) => {
+                state.page = (state.page + 1).min(TimerId::ALL.len() - 1);
+                redraw_full(&state, gcm).await;
+            }
+            (Mode::Timers, Action::PreviousItem) => {
+                state.page = state.page.saturating_sub(1);
+                redraw_full(&state, gcm).await;
+            }
+            (Mode::Timers, Action::SelectItem) => {
+                ACTION_UPSTREAM
+                    .send(Action::TimerStartStop(TimerId::ALL[state.page]))
+                    .await;
+            }
+            (Mode::Timers, Action::ResetItem) => {
+                ACTION_UPSTREAM
+                    .send(Action::TimerReset(TimerId::ALL[state.page]))
+                    .await;
+            }
+            (Mode::Events, Action::NextItem) => {
+                let len = STATE.lock().await.events.len();
+                state.page = (state.page + 1).min(len.saturating_sub(DIAG_LINES));
+                redraw_full(&state, gcm).await;
+            }
+            (Mode::Events, Action::PreviousItem) => {
+                state.page = state.page.saturating_sub(1);
+                redraw_full(&state, gcm).await;
+            }
+            (Mode::Diagnostics, Action::NextItem) => {
+                state.page = (state.page + 1).min(diagnostics::NUM_LINES - DIAG_LINES);
+                redraw_full(&state, gcm).await;
+            }
+            (Mode::Diagnostics, Action::PreviousItem) => {
+                state.page = state.page.saturating_sub(1);
+                redraw_full(&state, gcm).await;
+            }
             (_, Action::ModeChange(m)) => {
                 state.mode = m;
+                state.page = 0;
+                if m == Mode::Menu {
+                    state.menu_page = MenuPage::Root;
+                }
                 redraw_full(&state, gcm).await;
             }
             (_, Action::TextEntryStart { ctx, initial_value }) => {
@@ -81,32 +312,111 @@ pub async fn ui_task(mut gc: GraphicsController) {
                 need_redraw = true;
             }
             (_, Action::DebugMessage { msg }) => {
-                draw_debug_message(gcm, msg);
+                if toasts.push(Notification {
+                    priority: Priority::Info,
+                    msg,
+                }) {
+                    show_toast(gcm, &toasts).await;
+                }
+            }
+            (_, Action::Notify(n)) => {
+                if toasts.push(n) {
+                    show_toast(gcm, &toasts).await;
+                }
             }
             (_, Action::ForceRedraw) => redraw_full(&state, gcm).await,
+            // Nothing is playing on a core that has gone away
+            (_, Action::LoseConnection) => state.transport = TransportView::default(),
+            (Mode::Main, Action::LinkStatusChanged(link)) => {
+                let on_backup = STATE.lock().await.active_core != 0;
+                draw_main_link(gcm, layout::layout(), link, on_backup);
+                gcm.commit().await;
+            }
+            (mode, Action::NewTransportData(data)) => {
+                if !data.running {
+                    state.transport.jump_armed = false;
+                }
+                state.transport.playing = data.running;
+                state.transport.vlt = data.vlt;
+                if mode == Mode::Main {
+                    draw_main_status(gcm, layout::layout(), &state.transport);
+                    gcm.commit().await;
+                }
+            }
+            (
+                mode,
+                Action::RequestToCore(Request::ControlAction(ControlAction::ChangeJumpMode(
+                    change,
+                ))),
+            ) => {
+                // None of the transport fields we read carry the jump mode, so follow the changes
+                // we ask for. A change made on the core itself isn't seen until the transport
+                // stops or the cue changes, both of which disarm it
+                if matches!(change, JumpModeChange::Toggle) {
+                    state.transport.jump_armed = !state.transport.jump_armed;
+                }
+                if mode == Mode::Main {
+                    draw_main_status(gcm, layout::layout(), &state.transport);
+                    gcm.commit().await;
+                }
+            }
+            (mode, Action::RequestToCore(Request::ControlAction(ControlAction::TransportZero))) => {
+                state.transport.elapsed_us = 0;
+                state.transport.beat_len_us = 0;
+                if mode == Mode::Main {
+                    draw_main_status(gcm, layout::layout(), &state.transport);
+                    gcm.commit().await;
+                }
+            }
+            (mode, Action::NewBeatData(beat)) if mode != Mode::Main => {
+                state.transport.elapsed_us += state.transport.beat_len_us;
+                state.transport.beat_len_us = beat.length as u64;
+                state.bpm = beat.tempo();
+            }
             (Mode::Main, Action::NewBeatData(beat)) => {
+                state.transport.elapsed_us += state.transport.beat_len_us;
+                state.transport.beat_len_us = beat.length as u64;
+                draw_main_status(gcm, layout::layout(), &state.transport);
                 if state.bpm != beat.tempo() {
                     state.bpm = beat.tempo();
-                    draw_main_bpm(gcm, state.bpm);
+                    draw_main_bpm(gcm, layout::layout(), state.bpm);
                 }
                 if beat.count == 1 {
-                    draw_main_bar(gcm, beat);
+                    // The core doesn't tell us the time signature, so learn it from where it wraps
+                    if state.beat_count > 1 {
+                        state.beats_per_bar = state.beat_count;
+                    }
+                    draw_main_bar(gcm, layout::layout(), beat);
                 }
-                gcm.commit();
+                state.beat_count = beat.count as u8;
+                state.beats_per_bar = state.beats_per_bar.max(state.beat_count);
+                draw_main_beat(gcm, layout::layout(), state.beat_count, state.beats_per_bar);
+                gcm.commit().await;
             }
-            (Mode::Main, Action::NewCueData(idx, cue)) => {
-                if state.mode != Mode::Lock {
-                    draw_main_cue(gcm, idx, cue);
-                    gcm.commit();
+            (Mode::Main, Action::MetronomeBeat(count)) if !state.transport.playing => {
+                draw_main_beat(gcm, layout::layout(), count, metronome::BEATS_PER_BAR);
+                gcm.commit().await;
+            }
+            (mode, Action::NewCueData(idx, cue)) => {
+                state.transport.elapsed_us = 0;
+                state.transport.beat_len_us = 0;
+                state.transport.jump_armed = false;
+                if mode == Mode::Main {
+                    draw_main_cue(gcm, layout::layout(), idx, cue);
+                    draw_main_status(gcm, layout::layout(), &state.transport);
+                    gcm.commit().await;
                 }
             }
             (Mode::Main, Action::NewLabelData(label)) => {
-                draw_main_mark(gcm, label);
-                gcm.commit();
+                draw_main_mark(gcm, layout::layout(), label);
+                gcm.commit().await;
             }
-            (Mode::Main, Action::NewBPM(bpm)) => {
-                draw_main_bpm(gcm, bpm as u16);
-                gcm.commit();
+            (mode, Action::NewBPM(bpm)) => {
+                state.bpm = bpm as u16;
+                if mode == Mode::Main {
+                    draw_main_bpm(gcm, layout::layout(), state.bpm);
+                    gcm.commit().await;
+                }
             }
             _ => {}
         }
@@ -118,25 +428,60 @@ pub async fn ui_task(mut gc: GraphicsController) {
 }
 
 async fn redraw_full(state: &ViewState, gc: &mut GraphicsController) {
+    gc.set_offset(if state.mode == Mode::Main {
+        PIXEL_SHIFT[state.shift_step as usize % PIXEL_SHIFT.len()]
+    } else {
+        Point::zero()
+    });
     gc.clear();
+    let layout = layout::layout();
     let mut app_state = STATE.lock().await;
     match state.mode {
-        Mode::Lock => gc.logo(),
+        Mode::Lock => match state.screensaver {
+            Some(pos) => draw_screensaver(gc, pos),
+            None => gc.logo(),
+        },
         Mode::Main => {
-            draw_main_bpm(gc, app_state.beat.tempo());
-            draw_main_cue(gc, app_state.cue_idx, app_state.cue_metadata);
-            draw_main_mark(gc, app_state.mark_label);
-            draw_main_bar(gc, app_state.beat);
+            draw_main_bpm(gc, layout, state.bpm);
+            let mut cue = app_state.cue_metadata;
+            // The cue data has been cleared, say why rather than show an empty cue 0
+            if app_state.link == LinkStatus::Offline {
+                cue.human_ident = StaticString::new(LinkStatus::Offline.label());
+            }
+            draw_main_cue(gc, layout, app_state.cue_idx, cue);
+            draw_main_mark(gc, layout, app_state.mark_label);
+            draw_main_bar(gc, layout, app_state.beat);
+            draw_main_beat(gc, layout, state.beat_count, state.beats_per_bar);
+            draw_main_status(gc, layout, &state.transport);
+            draw_main_link(gc, layout, app_state.link, app_state.active_core != 0);
+            draw_main_timer(gc, layout, &app_state.timers);
         }
         Mode::Menu => {
-            draw_menu(gc, &mut app_state, state.selected_index);
+            draw_menu(gc, &mut app_state, state.menu_page, state.selected_index);
         }
         Mode::TextEntry => {
             draw_textentry(gc, &mut app_state, state.text);
         }
+        Mode::Diagnostics => {
+            draw_diagnostics(gc, state.page);
+        }
+        Mode::Network => {
+            draw_network(gc, &app_state);
+        }
+        Mode::Timers => {
+            draw_timers(gc, &app_state.timers, state.page);
+        }
+        Mode::Events => {
+            draw_events(gc, &app_state.events, state.page);
+        }
+        Mode::About => {
+            draw_about(gc, &app_state);
+        }
         _ => {}
     }
-    gc.commit();
+    // Don't hold up other tasks on STATE while the I2C transfer runs
+    drop(app_state);
+    gc.commit().await;
 }
 
 async fn redraw_partial(state: &ViewState, gc: &mut GraphicsController) {
@@ -145,17 +490,20 @@ async fn redraw_partial(state: &ViewState, gc: &mut GraphicsController) {
         Mode::TextEntry => {
             let mut app_state = STATE.lock().await;
             draw_textentry(gc, &mut app_state, state.text);
+            drop(app_state);
+            gc.commit().await;
         }
         _ => {}
     }
 }
 
-fn draw_main_bpm(gc: &mut GraphicsController, bpm: u16) -> Option<()> {
+fn draw_main_bpm(gc: &mut GraphicsController, layout: Layout, bpm: u16) -> Option<()> {
+    let origin = layout.place(Widget::Bpm)?;
     let mut buf = [0u8; 3];
     let s = format_no_std::show(&mut buf, format_args!("{: >3}", bpm)).unwrap_or_default();
     gc.text_strip(
         s,
-        Point::new(0, 0),
+        origin,
         GraphicsController::CHAR_SMALL,
         3,
         GraphicsController::TL_ALIGN,
@@ -163,27 +511,54 @@ fn draw_main_bpm(gc: &mut GraphicsController, bpm: u16) -> Option<()> {
     None
 }
 
-fn draw_main_cue(gc: &mut GraphicsController, idx: u16, cue: CueMetadata) -> Option<()> {
-    let mut buf = [0u8; 40];
-    let s = format_no_std::show(
-        &mut buf,
-        format_args!("{: >3}:{: <32}", idx, cue.human_ident.str()),
-    )
-    .unwrap_or_default();
+fn draw_main_cue(
+    gc: &mut GraphicsController,
+    layout: Layout,
+    idx: u16,
+    cue: CueMetadata,
+) -> Option<()> {
+    if let Some(origin) = layout.place(Widget::CueLarge) {
+        let mut buf = [0u8; 40];
+        let s = format_no_std::show(&mut buf, format_args!("{} {}", idx, cue.human_ident.str()))
+            .unwrap_or_default();
+        gc.marquee(
+            MarqueeSlot::Cue,
+            s,
+            origin,
+            GraphicsController::CHAR_LARGE,
+            12,
+        );
+        return None;
+    }
+    let origin = layout.place(Widget::Cue)?;
+    let mut buf = [0u8; 8];
+    let s = format_no_std::show(&mut buf, format_args!("{: >3}:", idx)).unwrap_or_default();
     gc.text_strip(
         s,
-        Point::new(24, 0),
+        origin,
         GraphicsController::CHAR_SMALL,
-        16,
+        4,
         GraphicsController::TL_ALIGN,
     );
+    gc.marquee(
+        MarqueeSlot::Cue,
+        cue.human_ident.str(),
+        origin + Size::new(24, 0),
+        GraphicsController::CHAR_SMALL,
+        12,
+    );
     None
 }
 
-fn draw_main_mark(gc: &mut GraphicsController, label: StaticString<8>) -> Option<()> {
+fn draw_main_mark(
+    gc: &mut GraphicsController,
+    layout: Layout,
+    label: StaticString<8>,
+) -> Option<()> {
+    // Labels are at most 8 characters, which always fit
     gc.text_strip(
         label.str(),
-        Point::new(0, 11),
+        layout.place(Widget::Mark)?,
         GraphicsController::CHAR_LARGE,
         8,
         GraphicsController::TL_ALIGN,
@@ -191,13 +566,17 @@ fn draw_main_mark(gc: &mut GraphicsController, label: StaticString<8>) -> Option
     None
 }
 
-fn draw_main_bar(gc: &mut GraphicsController, beat: Beat) -> Option<()> {
+fn draw_main_bar(gc: &mut GraphicsController, layout: Layout, beat: Beat) -> Option<()> {
     let mut buf = [0u8; 4];
     let s =
         format_no_std::show(&mut buf, format_args!("{: >3}", beat.bar_number)).unwrap_or_default();
+    if let Some(origin) = layout.place(Widget::BarGiant) {
+        gc.text_giant(s, origin, 3);
+    }
+    let origin = layout.place(Widget::Bar)?;
     gc.text_strip(
         s,
-        Point::new(0, 33),
+        origin,
         GraphicsController::CHAR_LARGE,
         3,
         GraphicsController::TL_ALIGN,
@@ -205,25 +584,137 @@ fn draw_main_bar(gc: &mut GraphicsController, beat: Beat) -> Option<()> {
     None
 }
 
-fn draw_menu(gc: &mut GraphicsController, app: &mut SystemState, start_idx: usize) {
+fn draw_main_beat(
+    gc: &mut GraphicsController,
+    layout: Layout,
+    count: u8,
+    beats_per_bar: u8,
+) -> Option<()> {
+    gc.beat_grid(layout.place(Widget::BeatGrid)?, count, beats_per_bar);
+    None
+}
+
+fn draw_main_status(
+    gc: &mut GraphicsController,
+    layout: Layout,
+    transport: &TransportView,
+) -> Option<()> {
+    let secs = transport.elapsed_us / 1_000_000;
+    let mut buf = [0u8; 16];
+    let playhead = format_no_std::show(&mut buf, format_args!("{:02}:{:02}", secs / 60, secs % 60))
+        .unwrap_or_default();
+    if let Some(origin) = layout.place(Widget::Playhead) {
+        gc.text_strip(
+            playhead,
+            origin,
+            GraphicsController::CHAR_LARGE,
+            5,
+            GraphicsController::TL_ALIGN,
+        );
+    }
+
+    let origin = layout.place(Widget::Status)?;
+    let mut buf = [0u8; 16];
+    let s = format_no_std::show(
+        &mut buf,
+        format_args!(
+            "{} {} {}",
+            if transport.playing { "PLAY" } else { "STOP" },
+            if transport.vlt { "V" } else { " " },
+            if transport.jump_armed { "J" } else { " " },
+        ),
+    )
+    .unwrap_or_default();
+    gc.text_strip(
+        s,
+        origin,
+        GraphicsController::CHAR_SMALL,
+        9,
+        GraphicsController::TL_ALIGN,
+    );
+    gc.text_strip(
+        playhead,
+        origin + Size::new(0, 10),
+        GraphicsController::CHAR_SMALL,
+        9,
+        GraphicsController::TL_ALIGN,
+    );
+    None
+}
+
+fn draw_main_link(
+    gc: &mut GraphicsController,
+    layout: Layout,
+    link: LinkStatus,
+    on_backup: bool,
+) -> Option<()> {
+    let origin = layout.place(Widget::Link)?;
+    let icon = match link {
+        LinkStatus::NoLink => "x",
+        LinkStatus::DhcpPending => "?",
+        // Following a backup core is fine, but nobody should be surprised by it
+        LinkStatus::Subscribed if on_backup => "B",
+        LinkStatus::Subscribed => "*",
+        LinkStatus::Stale => "!",
+        LinkStatus::Offline => "-",
+    };
+    gc.text_strip(
+        icon,
+        origin,
+        GraphicsController::CHAR_SMALL,
+        1,
+        GraphicsController::TL_ALIGN,
+    );
+    None
+}
+
+fn draw_main_timer(gc: &mut GraphicsController, layout: Layout, timers: &Timers) -> Option<()> {
+    let origin = layout.place(Widget::Timer)?;
+    let timer = timers.get(timers.featured);
+    gc.text_strip(
+        timers::format(timer.shown(Instant::now())).str(),
+        origin,
+        GraphicsController::CHAR_LARGE,
+        7,
+        GraphicsController::TL_ALIGN,
+    );
+    gc.text_strip(
+        timers.featured.label(),
+        origin + Size::new(0, GraphicsController::CHAR_LARGE.height),
+        GraphicsController::CHAR_SMALL,
+        11,
+        GraphicsController::TL_ALIGN,
+    );
+    None
+}
+
+fn draw_menu(gc: &mut GraphicsController, app: &mut SystemState, page: MenuPage, start_idx: usize) {
     const NUM_ITEMS: i32 = 4;
     const MARGIN: i32 = 3;
     const ITEM_HEIGHT: i32 = (64 - (NUM_ITEMS + 1) * MARGIN) / NUM_ITEMS;
-    for (i, item) in menu::get_items_following_idx::<4>(start_idx)
+    for (i, item) in menu::get_items_following_idx::<4>(page, start_idx)
         .iter()
         .flatten()
         .enumerate()
     {
         let offset_y = MARGIN + i as i32 * (ITEM_HEIGHT + MARGIN);
         let origin = Point::new(MARGIN, offset_y);
-        gc.list_item(
-            item.text.str(),
-            Some((item.value)(app.clone()).str()),
-            origin,
-            i == 0,
-        );
+        let value = (item.value)(app.clone());
+        // Whatever is left of the row after the label, in characters
+        let room = (19usize).saturating_sub(item.text.len() + 1);
+        if i == 0 && value.len() > room {
+            gc.list_item(item.text.str(), None, origin, true);
+            gc.marquee(
+                MarqueeSlot::MenuValue,
+                value.str(),
+                origin + Size::new(120 - room as u32 * 6, 2),
+                GraphicsController::CHAR_SMALL,
+                room,
+            );
+        } else {
+            gc.list_item(item.text.str(), Some(value.str()), origin, i == 0);
+        }
     }
-    gc.commit();
 }
 
 fn draw_textentry(gc: &mut GraphicsController, app: &mut SystemState, val: StaticString<32>) {
@@ -262,19 +753,237 @@ fn draw_textentry(gc: &mut GraphicsController, app: &mut SystemState, val: Stati
         val.len(),
         GraphicsController::TL_ALIGN,
     );
+}
+
+const DIAG_LINES: usize = 6;
 
-    gc.commit();
+fn draw_diagnostics(gc: &mut GraphicsController, first_line: usize) {
+    let snap = diagnostics::snapshot();
+    for i in 0..DIAG_LINES {
+        let line = diagnostics::line(&snap, first_line + i);
+        gc.text_strip(
+            line.str(),
+            Point::new(
+                0,
+                i as i32 * GraphicsController::CHAR_SMALL.height as i32 + 2,
+            ),
+            GraphicsController::CHAR_SMALL,
+            21,
+            GraphicsController::TL_ALIGN,
+        );
+    }
 }
 
-fn draw_debug_message(gc: &mut GraphicsController, msg: StaticString<32>) {
-    gc.clear();
+fn draw_network(gc: &mut GraphicsController, app: &SystemState) {
+    let snap = diagnostics::snapshot();
+    let now = Instant::now();
+    for i in 0..DIAG_LINES {
+        let mut buf = [0u8; 32];
+        let s = match i {
+            0 => format_no_std::show(&mut buf, format_args!("Link {}", app.link.label())),
+            1 => format_no_std::show(
+                &mut buf,
+                format_args!("Me   {}", app.self_ip.str_from_octets().str()),
+            ),
+            2 => format_no_std::show(
+                &mut buf,
+                format_args!(
+                    "Core {} {}",
+                    Core::role(app.active_core),
+                    app.core().ip.str_from_octets().str()
+                ),
+            ),
+            3 => match app.core().rtt_ms {
+                Some(rtt) => format_no_std::show(
+                    &mut buf,
+                    format_args!("Port {: <5} RTT{: >4}ms", app.core().ip.port, rtt),
+                ),
+                None => format_no_std::show(
+                    &mut buf,
+                    format_args!("Port {: <5} RTT   -", app.core().ip.port),
+                ),
+            },
+            4 => match app.core().last_heartbeat {
+                Some(t) => format_no_std::show(
+                    &mut buf,
+                    format_args!("Heartbeat {: >5}s ago", (now - t).as_secs()),
+                ),
+                None => format_no_std::show(&mut buf, format_args!("Heartbeat never")),
+            },
+            _ => format_no_std::show(
+                &mut buf,
+                format_args!("RX{: >7} TX{: >7}", snap.rx, snap.tx),
+            ),
+        }
+        .unwrap_or_default();
+        gc.text_strip(
+            s,
+            Point::new(
+                0,
+                i as i32 * GraphicsController::CHAR_SMALL.height as i32 + 2,
+            ),
+            GraphicsController::CHAR_SMALL,
+            21,
+            GraphicsController::TL_ALIGN,
+        );
+    }
+}
+
+/// Time since, in the largest whole unit, to fit three characters.
+fn age(d: Duration) -> StaticString<8> {
+    let secs = d.as_secs();
+    let mut buf = [0u8; 8];
+    let s = match secs {
+        0..60 => format_no_std::show(&mut buf, format_args!("{}s", secs)),
+        60..3600 => format_no_std::show(&mut buf, format_args!("{}m", secs / 60)),
+        _ => format_no_std::show(&mut buf, format_args!("{}h", (secs / 3600).min(99))),
+    }
+    .unwrap_or_default();
+    StaticString::new(s)
+}
+
+/// Newest at the top, each with how long ago it was and the cue it happened in.
+fn draw_events(gc: &mut GraphicsController, events: &EventLog, first_line: usize) {
+    let now = Instant::now();
+    let mut lines = events.recent().skip(first_line);
+    for i in 0..DIAG_LINES {
+        let mut buf = [0u8; 32];
+        let s = match lines.next() {
+            Some(entry) => format_no_std::show(
+                &mut buf,
+                format_args!(
+                    "{: >3} {: >3} {}",
+                    age(now - entry.at).str(),
+                    entry.cue_idx,
+                    entry.text.str()
+                ),
+            )
+            .unwrap_or_default(),
+            None if i == 0 && first_line == 0 => "No events yet",
+            None => "",
+        };
+        gc.text_strip(
+            s,
+            Point::new(
+                0,
+                i as i32 * GraphicsController::CHAR_SMALL.height as i32 + 2,
+            ),
+            GraphicsController::CHAR_SMALL,
+            21,
+            GraphicsController::TL_ALIGN,
+        );
+    }
+}
+
+fn draw_about(gc: &mut GraphicsController, app: &SystemState) {
+    // Names run up to 32 characters, longer ones scroll
+    let name = app.device_name();
+    if name.len() > 21 {
+        gc.marquee(
+            MarqueeSlot::About,
+            name,
+            Point::new(0, 2),
+            GraphicsController::CHAR_SMALL,
+            21,
+        );
+    }
+    for i in 0..5 {
+        let mut buf = [0u8; 32];
+        let s = match i {
+            0 if name.len() > 21 => continue,
+            0 => Some(name),
+            1 => format_no_std::show(&mut buf, format_args!("MAC {}", app.mac)).ok(),
+            2 => match app.unique_id {
+                Some(id) => format_no_std::show(&mut buf, format_args!("ID  {:016x}", id)).ok(),
+                None => Some("ID  unreadable"),
+            },
+            3 => format_no_std::show(
+                &mut buf,
+                format_args!("IP  {}", app.self_ip.str_from_octets().str()),
+            )
+            .ok(),
+            _ => format_no_std::show(&mut buf, format_args!("FW  {}", env!("CARGO_PKG_VERSION")))
+                .ok(),
+        }
+        .unwrap_or_default();
+        gc.text_strip(
+            s,
+            Point::new(
+                0,
+                i as i32 * GraphicsController::CHAR_SMALL.height as i32 + 2,
+            ),
+            GraphicsController::CHAR_SMALL,
+            21,
+            GraphicsController::TL_ALIGN,
+        );
+    }
+}
+
+fn screensaver_pos(seed: u64) -> Point {
+    // Keep clear of the banner at the bottom
+    let seed = seed / 1000;
+    Point::new((seed % (128 - 36)) as i32, ((seed / 7) % (52 - 10)) as i32)
+}
+
+fn draw_screensaver(gc: &mut GraphicsController, pos: Point) {
     gc.text_strip(
-        msg.str(),
-        Point::new(0, 0),
+        "ClicKS",
+        pos,
         GraphicsController::CHAR_SMALL,
-        msg.len(),
+        6,
         GraphicsController::TL_ALIGN,
     );
+}
+
+fn draw_timers(gc: &mut GraphicsController, timers: &Timers, selected: usize) {
+    let now = Instant::now();
+    for (i, id) in TimerId::ALL.iter().enumerate() {
+        let timer = timers.get(*id);
+        let mut buf = [0u8; 32];
+        let s = format_no_std::show(
+            &mut buf,
+            format_args!(
+                "{}{: <9}{: >8} {}",
+                if i == selected { ">" } else { " " },
+                id.label(),
+                timers::format(timer.shown(now)).str(),
+                if timer.expired {
+                    "!"
+                } else if timer.running() {
+                    ">"
+                } else {
+                    " "
+                },
+            ),
+        )
+        .unwrap_or_default();
+        gc.text_strip(
+            s,
+            Point::new(
+                0,
+                i as i32 * GraphicsController::CHAR_SMALL.height as i32 + 2,
+            ),
+            GraphicsController::CHAR_SMALL,
+            21,
+            GraphicsController::TL_ALIGN,
+        );
+    }
+    if let Some(id) = TimerId::ALL.get(selected) {
+        gc.text_strip(
+            timers::format(timers.get(*id).shown(now)).str(),
+            // Clear of the rows above, which end at y 32
+            Point::new(0, 33),
+            GraphicsController::CHAR_LARGE,
+            8,
+            GraphicsController::TL_ALIGN,
+        );
+    }
+}
 
-    gc.commit();
+async fn show_toast(gc: &mut GraphicsController, toasts: &Toasts) {
+    match toasts.current() {
+        Some(n) => gc.show_banner(n.msg.str()),
+        None => gc.hide_banner(),
+    }
+    gc.commit().await;
 }